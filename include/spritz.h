@@ -0,0 +1,76 @@
+/*
+ * C interface to the spritz crate, as built by
+ *
+ *   cargo rustc --release --features ffi --crate-type staticlib
+ *
+ * or --crate-type cdylib. see src/ffi.rs.
+ *
+ * every mode is an opaque context: create it with *_new, feed it with
+ * *_update and free it with *_finalize, which writes the result. passing
+ * NULL as the output of *_finalize frees the context without writing. a
+ * context must be finalized exactly once, and used by one thread at a time.
+ * data pointers may be NULL when their length is 0.
+ */
+
+#ifndef SPRITZ_H
+#define SPRITZ_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define SPRITZ_KEY_LEN 32
+#define SPRITZ_NONCE_LEN 16
+#define SPRITZ_TAG_LEN 32
+#define SPRITZ_DIGEST_LEN 32
+
+typedef struct spritz_hash spritz_hash;
+typedef struct spritz_mac spritz_mac;
+typedef struct spritz_stream spritz_stream;
+typedef struct spritz_sealer spritz_sealer;
+typedef struct spritz_opener spritz_opener;
+
+/* the 32 byte hash. */
+spritz_hash *spritz_hash_new(void);
+void spritz_hash_update(spritz_hash *ctx, const uint8_t *data, size_t len);
+/* writes SPRITZ_DIGEST_LEN bytes. */
+void spritz_hash_finalize(spritz_hash *ctx, uint8_t *out);
+
+/* the 32 byte MAC, with a key of any length. */
+spritz_mac *spritz_mac_new(const uint8_t *key, size_t key_len);
+void spritz_mac_update(spritz_mac *ctx, const uint8_t *data, size_t len);
+/* writes SPRITZ_TAG_LEN bytes. */
+void spritz_mac_finalize(spritz_mac *ctx, uint8_t *out);
+
+/* the stream cipher, keyed with key and then iv; iv_len may be 0. */
+spritz_stream *spritz_stream_new(const uint8_t *key, size_t key_len, const uint8_t *iv, size_t iv_len);
+/* encrypts or decrypts buf in place, continuing the keystream. */
+void spritz_stream_xor(spritz_stream *ctx, uint8_t *buf, size_t len);
+/* wipes and frees ctx. NULL is ignored. */
+void spritz_stream_free(spritz_stream *ctx);
+
+/*
+ * the AEAD, with a SPRITZ_KEY_LEN byte key and a SPRITZ_NONCE_LEN byte
+ * nonce that must never repeat under the key. *_new return NULL if key or
+ * nonce is NULL.
+ */
+spritz_sealer *spritz_seal_new(const uint8_t *key, const uint8_t *nonce, const uint8_t *ad, size_t ad_len);
+/* encrypts buf in place. */
+void spritz_seal_update(spritz_sealer *ctx, uint8_t *buf, size_t len);
+/* writes the SPRITZ_TAG_LEN byte tag. */
+void spritz_seal_finalize(spritz_sealer *ctx, uint8_t *tag);
+
+spritz_opener *spritz_open_new(const uint8_t *key, const uint8_t *nonce, const uint8_t *ad, size_t ad_len);
+/* decrypts buf in place; none of it may be used until the tag checks. */
+void spritz_open_update(spritz_opener *ctx, uint8_t *buf, size_t len);
+/* 0 if tag is right, -1 if not or if tag is NULL. frees ctx either way. */
+int spritz_open_finalize(spritz_opener *ctx, const uint8_t *tag);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
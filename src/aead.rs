@@ -0,0 +1,426 @@
+// authenticated encryption with associated data, after section 4 of the
+// paper: key, nonce and header are absorbed with stops between them, the
+// message goes through the duplex, and the tag length is absorbed before
+// the tag is squeezed. sealed messages are ciphertext || tag.
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(all(feature = "nonce-guard", test))]
+use std::cell::Cell;
+#[cfg(feature = "nonce-guard")]
+use std::collections::VecDeque;
+#[cfg(feature = "nonce-guard")]
+use std::sync::Mutex;
+#[cfg(feature = "std")]
+use std::thread;
+
+use duplex::{SpritzDuplex, BLOCK};
+use error::{Error, Result};
+#[cfg(feature = "nonce-guard")]
+use key::Fingerprint;
+use key::{Key, Nonce, NONCE_LEN};
+use metrics::{Counters, Metrics};
+use sponge::SpongePermutation;
+use Spritz;
+
+// the tag is the MAC's; it lives there so the mac feature works alone.
+pub use mac::{Tag, TAG_LEN};
+
+// how many (key, nonce) pairs the nonce-guard feature remembers.
+#[cfg(feature = "nonce-guard")]
+const GUARD_LEN: usize = 4096;
+
+// holds the state after key setup, so each message starts from a clone of
+// it rather than absorbing the key again.
+#[derive(Clone, Debug)]
+pub struct SpritzAead<P = Spritz> {
+	keyed: SpritzDuplex<P>,
+	counters: Counters,
+	#[cfg(feature = "nonce-guard")]
+	id: Fingerprint,
+}
+
+impl SpritzAead {
+	pub const KEY_SIZE: usize = ::key::KEY_LEN;
+	pub const NONCE_SIZE: usize = NONCE_LEN;
+	pub const TAG_SIZE: usize = TAG_LEN;
+
+	pub fn new(key: &Key) -> SpritzAead {
+		SpritzAead::with_permutation(key)
+	}
+}
+
+impl<P: SpongePermutation> SpritzAead<P> {
+	pub fn with_permutation(key: &Key) -> SpritzAead<P> {
+		SpritzAead {
+			keyed: SpritzDuplex::with_permutation(key.as_bytes()),
+			counters: Counters::default(),
+			#[cfg(feature = "nonce-guard")]
+			id: key.fingerprint(),
+		}
+	}
+
+	// with the nonce-guard feature, debug builds remember the last GUARD_LEN
+	// (key, nonce) pairs sealed under, across every SpritzAead in the
+	// process, and panic when one comes round again. two messages under one
+	// nonce give away their xor and the means to forge tags, so it's caught
+	// in development rather than left to chance.
+	fn guard(&self, nonce: &Nonce) {
+		#[cfg(feature = "nonce-guard")]
+		if cfg!(debug_assertions) && guarding() {
+			static SEEN: Mutex<VecDeque<(Fingerprint, Nonce)>> = Mutex::new(VecDeque::new());
+			assert!(remember(&SEEN, self.id, *nonce), "spritz: nonce reused under the same key");
+		}
+		let _ = nonce;
+	}
+
+	// messages through sealer() and opener() are counted here, when they
+	// start; their bytes and shuffles aren't counted.
+	fn start(&self, nonce: &Nonce, ad: &[u8]) -> SpritzDuplex<P> {
+		self.counters.add(Metrics { messages: 1, ..Metrics::default() });
+		let mut d = self.keyed.clone();
+		d.absorb(nonce.as_bytes());
+		d.absorb_stop();
+		d.absorb(ad);
+		d.absorb_stop();
+		d.absorb(&[TAG_LEN as u8]);
+		d
+	}
+
+	fn finish(d: &mut SpritzDuplex<P>) -> Tag {
+		d.absorb(&[TAG_LEN as u8]);
+		let mut t = [0; TAG_LEN];
+		d.squeeze(&mut t);
+		Tag::new(t)
+	}
+
+	pub fn seal_in_place_detached(&self, nonce: &Nonce, ad: &[u8], buf: &mut [u8]) -> Tag {
+		self.guard(nonce);
+		let mut d = self.start(nonce, ad);
+		d.seal(buf);
+		let t = SpritzAead::finish(&mut d);
+		self.count(&d, buf.len(), false);
+		t
+	}
+
+	// on failure buf is zeroed rather than left holding unauthenticated plaintext.
+	pub fn open_in_place_detached(&self, nonce: &Nonce, ad: &[u8], buf: &mut [u8], tag: &Tag) -> Result<()> {
+		let mut d = self.start(nonce, ad);
+		d.open(buf);
+		let ok = SpritzAead::finish(&mut d).ct_eq(tag);
+		self.count(&d, buf.len(), !ok);
+		if ok {
+			Ok(())
+		} else {
+			for v in buf.iter_mut() {
+				*v = 0;
+			}
+			Err(Error::InvalidTag)
+		}
+	}
+
+	fn count(&self, d: &SpritzDuplex<P>, bytes: usize, failed: bool) {
+		let shuffles = d.usage().shuffles - self.keyed.usage().shuffles;
+		self.counters.add(Metrics { bytes: bytes as u64, shuffles, failures: failed as u64, ..Metrics::default() });
+	}
+
+	pub fn metrics(&self) -> Metrics {
+		self.counters.get()
+	}
+
+	// overwrite the keyed state; see Spritz::wipe.
+	pub fn wipe(&mut self) {
+		self.keyed.wipe();
+	}
+
+	pub fn sealer(&self, nonce: &Nonce, ad: &[u8]) -> Sealer<P> {
+		self.guard(nonce);
+		Sealer { c: Chunks::new(self.start(nonce, ad)) }
+	}
+
+	pub fn opener(&self, nonce: &Nonce, ad: &[u8]) -> Opener<P> {
+		Opener { c: Chunks::new(self.start(nonce, ad)) }
+	}
+
+	// appends the tag to buf.
+	#[cfg(feature = "alloc")]
+	pub fn encrypt_in_place(&self, nonce: &Nonce, ad: &[u8], buf: &mut Vec<u8>) {
+		let tag = self.seal_in_place_detached(nonce, ad, buf);
+		buf.extend_from_slice(tag.as_bytes());
+	}
+
+	// strips the tag from buf, leaving the plaintext.
+	#[cfg(feature = "alloc")]
+	pub fn decrypt_in_place(&self, nonce: &Nonce, ad: &[u8], buf: &mut Vec<u8>) -> Result<()> {
+		if buf.len() < TAG_LEN {
+			return Err(Error::InvalidLength);
+		}
+		let mut tag = [0; TAG_LEN];
+		let n = buf.len() - TAG_LEN;
+		tag.copy_from_slice(&buf[n ..]);
+		buf.truncate(n);
+		self.open_in_place_detached(nonce, ad, buf, &Tag::new(tag))
+	}
+
+	#[cfg(feature = "alloc")]
+	pub fn seal(&self, nonce: &Nonce, ad: &[u8], msg: &[u8]) -> Vec<u8> {
+		let mut out = Vec::with_capacity(msg.len() + TAG_LEN);
+		out.extend_from_slice(msg);
+		let tag = self.seal_in_place_detached(nonce, ad, &mut out);
+		out.extend_from_slice(tag.as_bytes());
+		out
+	}
+
+	#[cfg(feature = "alloc")]
+	pub fn open(&self, nonce: &Nonce, ad: &[u8], sealed: &[u8]) -> Result<Vec<u8>> {
+		if sealed.len() < TAG_LEN {
+			return Err(Error::InvalidLength);
+		}
+		let (ct, t) = sealed.split_at(sealed.len() - TAG_LEN);
+		let mut tag = [0; TAG_LEN];
+		tag.copy_from_slice(t);
+		let mut out = ct.to_vec();
+		self.open_in_place_detached(nonce, ad, &mut out, &Tag::new(tag))?;
+		Ok(out)
+	}
+}
+
+// seal_in_place_detached and open_in_place_detached a piece at a time, so
+// a message needn't be in memory at once. the result is the same however it
+// is split: each duplex block's keystream is squeezed as its bytes arrive,
+// and its ciphertext held back until the block is full, since absorbing it
+// ends the squeeze.
+pub struct Sealer<P = Spritz> {
+	c: Chunks<P>,
+}
+
+impl<P: SpongePermutation> Sealer<P> {
+	pub fn update(&mut self, buf: &mut [u8]) {
+		self.c.update(buf, true);
+	}
+
+	pub fn finalize(self) -> Tag {
+		SpritzAead::finish(&mut self.c.finish())
+	}
+}
+
+pub struct Opener<P = Spritz> {
+	c: Chunks<P>,
+}
+
+impl<P: SpongePermutation> Opener<P> {
+	// the plaintext is unauthenticated until verify succeeds, and must be
+	// thrown away if it doesn't; it can't be zeroed here as it was for
+	// open_in_place_detached.
+	pub fn update(&mut self, buf: &mut [u8]) {
+		self.c.update(buf, false);
+	}
+
+	pub fn verify(self, tag: &Tag) -> Result<()> {
+		if SpritzAead::finish(&mut self.c.finish()).ct_eq(tag) {
+			Ok(())
+		} else {
+			Err(Error::InvalidTag)
+		}
+	}
+}
+
+struct Chunks<P> {
+	d: SpritzDuplex<P>,
+	ct: [u8; BLOCK],
+	len: usize,
+}
+
+impl<P: SpongePermutation> Chunks<P> {
+	fn new(mut d: SpritzDuplex<P>) -> Chunks<P> {
+		d.absorb_stop();
+		Chunks { d, ct: [0; BLOCK], len: 0 }
+	}
+
+	fn update(&mut self, buf: &mut [u8], seal: bool) {
+		let mut rest = buf;
+		while !rest.is_empty() {
+			let n = (BLOCK - self.len).min(rest.len());
+			let (head, tail) = rest.split_at_mut(n);
+			let ct = &mut self.ct[self.len .. self.len + n];
+			if seal {
+				self.d.apply_keystream(head);
+				ct.copy_from_slice(head);
+			} else {
+				ct.copy_from_slice(head);
+				self.d.apply_keystream(head);
+			}
+			self.len += n;
+			if self.len == BLOCK {
+				self.d.absorb(&self.ct);
+				self.len = 0;
+			}
+			rest = tail;
+		}
+	}
+
+	fn finish(mut self) -> SpritzDuplex<P> {
+		self.d.absorb(&self.ct[.. self.len]);
+		self.d.absorb_stop();
+		self.d
+	}
+}
+
+// message i is sealed under base_nonce.xor_counter(i), with no associated
+// data, so a batch uses up the base nonce and the next messages.len() - 1
+// after it.
+#[cfg(feature = "alloc")]
+pub fn seal_batch(key: &Key, base_nonce: &Nonce, messages: &[&[u8]]) -> Vec<Vec<u8>> {
+	let a = SpritzAead::new(key);
+	messages.iter().enumerate().map(|(i, m)| a.seal(&base_nonce.xor_counter(i as u64), b"", m)).collect()
+}
+
+// the same output as seal_batch, split across threads.
+#[cfg(feature = "std")]
+pub fn seal_batch_parallel(key: &Key, base_nonce: &Nonce, messages: &[&[u8]], threads: usize) -> Vec<Vec<u8>> {
+	let a = SpritzAead::new(key);
+	let mut out = vec![vec![]; messages.len()];
+	let per = messages.len().div_ceil(threads.max(1)).max(1);
+	thread::scope(|s| {
+		for (t, dst) in out.chunks_mut(per).enumerate() {
+			let a = &a;
+			s.spawn(move || {
+				for (j, v) in dst.iter_mut().enumerate() {
+					let i = t * per + j;
+					*v = a.seal(&base_nonce.xor_counter(i as u64), b"", messages[i]);
+				}
+			});
+		}
+	});
+	out
+}
+
+// this crate's own tests reuse nonces on purpose to compare outputs, so in
+// them the guard only runs on a thread that turns it on.
+#[cfg(all(feature = "nonce-guard", test))]
+thread_local!(static GUARD_IN_TEST: Cell<bool> = const { Cell::new(false) });
+
+#[cfg(all(feature = "nonce-guard", test))]
+fn guarding() -> bool {
+	GUARD_IN_TEST.with(Cell::get)
+}
+
+#[cfg(all(feature = "nonce-guard", not(test)))]
+fn guarding() -> bool {
+	true
+}
+
+// false if the pair is already in seen.
+#[cfg(feature = "nonce-guard")]
+fn remember(seen: &Mutex<VecDeque<(Fingerprint, Nonce)>>, id: Fingerprint, nonce: Nonce) -> bool {
+	let mut seen = seen.lock().unwrap_or_else(|e| e.into_inner());
+	if seen.contains(&(id, nonce)) {
+		return false;
+	}
+	if seen.len() == GUARD_LEN {
+		seen.pop_front();
+	}
+	seen.push_back((id, nonce));
+	true
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn aead() {
+	let a = SpritzAead::new(&Key::new([7; 32]));
+	let n = Nonce::from_counter(1);
+	let sealed = a.seal(&n, b"header", b"hello world");
+	assert_eq!(sealed.len(), 11 + TAG_LEN);
+	assert_eq!(a.open(&n, b"header", &sealed).unwrap(), b"hello world");
+	assert_eq!(a.open(&n, b"other", &sealed), Err(Error::InvalidTag));
+	assert_eq!(a.open(&Nonce::from_counter(2), b"header", &sealed), Err(Error::InvalidTag));
+	assert!(a.seal(&Nonce::from_counter(2), b"header", b"hello world") != sealed);
+
+	let mut bad = sealed.clone();
+	bad[0] ^= 1;
+	assert_eq!(a.open(&n, b"header", &bad), Err(Error::InvalidTag));
+	assert_eq!(a.open(&n, b"header", &sealed[.. 4]), Err(Error::InvalidLength));
+
+	let mut buf = b"hello world".to_vec();
+	a.encrypt_in_place(&n, b"header", &mut buf);
+	assert_eq!(buf, sealed);
+	a.decrypt_in_place(&n, b"header", &mut buf).unwrap();
+	assert_eq!(buf, b"hello world");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn chunked() {
+	let a = SpritzAead::new(&Key::new([7; 32]));
+	let n = Nonce::from_counter(3);
+	let msg: Vec<u8> = (0 .. 300u32).map(|i| i as u8).collect();
+	let mut whole = msg.clone();
+	let tag = a.seal_in_place_detached(&n, b"ad", &mut whole);
+	for split in &[0, 1, 63, 64, 65, 200, 300] {
+		let mut buf = msg.clone();
+		let mut s = a.sealer(&n, b"ad");
+		let (x, y) = buf.split_at_mut(*split);
+		s.update(x);
+		for c in y.chunks_mut(7) {
+			s.update(c);
+		}
+		assert_eq!(s.finalize(), tag);
+		assert_eq!(buf, whole);
+
+		let mut o = a.opener(&n, b"ad");
+		let (x, y) = buf.split_at_mut(*split);
+		o.update(&mut []);
+		o.update(x);
+		o.update(y);
+		assert_eq!(o.verify(&tag), Ok(()));
+		assert_eq!(buf, msg);
+	}
+	let mut o = a.opener(&n, b"other");
+	o.update(&mut whole);
+	assert_eq!(o.verify(&tag), Err(Error::InvalidTag));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn batch() {
+	let key = Key::new([8; 32]);
+	let n = Nonce::from_counter(100);
+	let msgs: Vec<&[u8]> = vec![b"one", b"two", b"", b"four"];
+	let sealed = seal_batch(&key, &n, &msgs);
+	let a = SpritzAead::new(&key);
+	for (i, m) in msgs.iter().enumerate() {
+		assert_eq!(&a.open(&n.xor_counter(i as u64), b"", &sealed[i]).unwrap()[..], *m);
+	}
+	assert_eq!(seal_batch_parallel(&key, &n, &msgs, 3), sealed);
+	assert_eq!(seal_batch_parallel(&key, &n, &msgs, 0), sealed);
+	assert!(seal_batch_parallel(&key, &n, &[], 4).is_empty());
+}
+
+#[cfg(feature = "nonce-guard")]
+#[test]
+fn nonce_guard() {
+	let seen = Mutex::new(VecDeque::new());
+	let (a, b) = (Key::new([1; 32]).fingerprint(), Key::new([2; 32]).fingerprint());
+	assert!(remember(&seen, a, Nonce::from_counter(0)));
+	assert!(remember(&seen, b, Nonce::from_counter(0)));
+	assert!(!remember(&seen, a, Nonce::from_counter(0)));
+	for n in 1 ..= GUARD_LEN as u64 {
+		assert!(remember(&seen, a, Nonce::from_counter(n)));
+	}
+	// long enough ago to be forgotten.
+	assert!(remember(&seen, a, Nonce::from_counter(0)));
+
+	// sealing twice under one nonce panics in debug builds, through either
+	// entry point; opening doesn't count.
+	GUARD_IN_TEST.with(|g| g.set(true));
+	let aead = SpritzAead::new(&Key::new([0x47; 32]));
+	let n = Nonce::from_counter(1);
+	aead.seal_in_place_detached(&n, b"", &mut [0; 4]);
+	let tag = aead.seal_in_place_detached(&Nonce::from_counter(2), b"", &mut [0; 4]);
+	let _ = aead.open_in_place_detached(&Nonce::from_counter(2), b"", &mut [0; 4], &tag);
+	let again = |f: &dyn Fn()| ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(f)).is_err();
+	assert_eq!(again(&|| { aead.seal_in_place_detached(&n, b"", &mut [0; 4]); }), cfg!(debug_assertions));
+	assert_eq!(again(&|| { let _ = aead.sealer(&n, b"other ad"); }), cfg!(debug_assertions));
+	assert!(!again(&|| { aead.seal_in_place_detached(&Nonce::from_counter(3), b"", &mut [0; 4]); }));
+	GUARD_IN_TEST.with(|g| g.set(false));
+}
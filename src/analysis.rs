@@ -0,0 +1,383 @@
+// read-only access to the internal state, and weakened variants of the
+// shuffle, for cryptanalysis. nothing here is needed to use the cipher, and
+// the state is secret in normal use.
+
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+
+use error::{Error, Result};
+use reduced::SpritzN;
+use {Phase, Spritz, N};
+
+impl Spritz {
+	pub fn S(&self) -> &[u8; N] {
+		&self.S
+	}
+
+	pub fn i(&self) -> u8 {
+		self.i
+	}
+
+	pub fn j(&self) -> u8 {
+		self.j
+	}
+
+	pub fn k(&self) -> u8 {
+		self.k
+	}
+
+	pub fn z(&self) -> u8 {
+		self.z
+	}
+
+	pub fn a(&self) -> u8 {
+		self.a
+	}
+
+	pub fn w(&self) -> u8 {
+		self.w
+	}
+}
+
+// the shape of shuffle(): passes rounds of whip(whip_len) then crush(),
+// and a last whip(whip_len). FULL is the one in the paper.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Rounds {
+	pub passes: usize,
+	pub whip_len: usize,
+}
+
+impl Rounds {
+	pub const FULL: Rounds = Rounds { passes: 2, whip_len: 2 * N };
+}
+
+// candidates for the output function, after update(). Spritz is the one
+// the paper settled on; the others are from the designs it compared.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Output {
+	// z = S[j + S[i + S[z + k]]].
+	Spritz,
+	// z = S[j + S[i + S[k]]], spritz without feeding z back.
+	NoFeedback,
+	// z = S[S[i] + S[j]], as in RC4.
+	Rc4,
+	// z = S[S[S[j]] + 1], as in VMPC.
+	Vmpc,
+}
+
+// spritz with its shuffle replaced by the one rounds describes and, if
+// output() is used, its output function by another; the rest, absorbing
+// and the stops, is unchanged.
+#[derive(Clone, Debug)]
+pub struct Weakened {
+	sp: Spritz,
+	rounds: Rounds,
+	output: Output,
+}
+
+impl Weakened {
+	pub fn new(key: &[u8], rounds: Rounds) -> Weakened {
+		let mut w = Weakened { sp: Spritz::initialize_state(), rounds, output: Output::Spritz };
+		w.absorb(key);
+		w
+	}
+
+	pub fn output(mut self, output: Output) -> Weakened {
+		self.output = output;
+		self
+	}
+
+	pub fn state(&self) -> &Spritz {
+		&self.sp
+	}
+
+	fn shuffle(&mut self) {
+		for _ in 0 .. self.rounds.passes {
+			self.sp.whip(self.rounds.whip_len);
+			self.sp.crush();
+		}
+		self.sp.whip(self.rounds.whip_len);
+		self.sp.a = 0;
+		self.sp.usage.shuffles += 1;
+	}
+
+	pub fn absorb(&mut self, data: &[u8]) {
+		for b in data {
+			self.absorb_nibble(b & 0xf);
+			self.absorb_nibble(b >> 4);
+		}
+		self.sp.usage.absorbed += data.len() as u64;
+		self.sp.phase = Phase::Absorbing;
+	}
+
+	fn absorb_nibble(&mut self, x: u8) {
+		if self.sp.a as usize == N / 2 {
+			self.shuffle();
+		}
+		self.sp.swap_nibble(self.sp.a, x);
+		self.sp.a = self.sp.a.wrapping_add(1);
+	}
+
+	pub fn absorb_stop(&mut self) {
+		if self.sp.a as usize == N / 2 {
+			self.shuffle();
+		}
+		self.sp.a = self.sp.a.wrapping_add(1);
+		self.sp.phase = Phase::Absorbing;
+	}
+
+	pub fn drip(&mut self) -> u8 {
+		if self.sp.a > 0 {
+			self.shuffle();
+		}
+		self.sp.update();
+		self.sp.usage.squeezed += 1;
+		self.sp.phase = Phase::Squeezing;
+		let (i, j, k, z) = (self.sp.i, self.sp.j, self.sp.k, self.sp.z);
+		let S = &self.sp.S;
+		let at = |x: u8| S[x as usize];
+		self.sp.z = match self.output {
+			Output::Spritz => at(j.wrapping_add(at(i.wrapping_add(at(z.wrapping_add(k)))))),
+			Output::NoFeedback => at(j.wrapping_add(at(i.wrapping_add(at(k))))),
+			Output::Rc4 => at(at(i).wrapping_add(at(j))),
+			Output::Vmpc => at(at(at(j)).wrapping_add(1)),
+		};
+		self.sp.z
+	}
+
+	pub fn squeeze_into(&mut self, out: &mut [u8]) {
+		for v in out.iter_mut() {
+			*v = self.drip();
+		}
+	}
+}
+
+// the first len bytes of keystream for each key base ^ delta, in the
+// order of deltas. the keys are absorbed from a shared state up to the
+// first byte where their delta is nonzero, walking one state along base in
+// that order, so a set of deltas that differ late costs little more than
+// one key setup. every delta must be as long as base.
+pub fn related_key_keystreams(base: &[u8], deltas: &[&[u8]], len: usize) -> Result<Vec<Vec<u8>>> {
+	if deltas.iter().any(|d| d.len() != base.len()) {
+		return Err(Error::InvalidLength);
+	}
+	let first = |d: &[u8]| d.iter().position(|v| *v != 0).unwrap_or(base.len());
+	let mut order: Vec<usize> = (0 .. deltas.len()).collect();
+	order.sort_by_key(|&i| first(deltas[i]));
+
+	let mut out = vec![vec![]; deltas.len()];
+	let mut sp = Spritz::initialize_state();
+	let mut at = 0;
+	for i in order {
+		let p = first(deltas[i]);
+		sp.absorb(&base[at .. p]);
+		at = p;
+		let mut t = sp.clone();
+		let rest: Vec<u8> = base[p ..].iter().zip(&deltas[i][p ..]).map(|(b, d)| b ^ d).collect();
+		t.absorb(&rest);
+		out[i] = vec![0; len];
+		t.squeeze_into(&mut out[i]);
+	}
+	Ok(out)
+}
+
+// where a sequence x, f(x), f(f(x)), .. starts repeating: after tail
+// steps it cycles with this period.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Cycle {
+	pub tail: u64,
+	pub period: u64,
+}
+
+// brent's algorithm on the sequence from x by step, or None if it needs more
+// than limit steps. it keeps two values, so it works on states far too big
+// to remember the ones seen.
+pub fn find_cycle<T: Clone + PartialEq>(x: &T, mut step: impl FnMut(&mut T), limit: u64) -> Option<Cycle> {
+	let mut steps = 0;
+	let mut next = |v: &mut T| {
+		steps += 1;
+		step(v);
+		steps <= limit
+	};
+
+	let (mut power, mut period) = (1, 1);
+	let mut tortoise = x.clone();
+	let mut hare = x.clone();
+	if !next(&mut hare) {
+		return None;
+	}
+	while tortoise != hare {
+		if power == period {
+			tortoise = hare.clone();
+			power *= 2;
+			period = 0;
+		}
+		if !next(&mut hare) {
+			return None;
+		}
+		period += 1;
+	}
+
+	let mut tortoise = x.clone();
+	let mut hare = x.clone();
+	for _ in 0 .. period {
+		if !next(&mut hare) {
+			return None;
+		}
+	}
+	let mut tail = 0;
+	while tortoise != hare {
+		if !next(&mut tortoise) || !next(&mut hare) {
+			return None;
+		}
+		tail += 1;
+	}
+	Some(Cycle { tail, period })
+}
+
+// the cycle of the whole state, S and the registers, under drip(): the
+// keystream repeats with the period, and usually sooner. only small N have
+// periods short enough to find; the update is invertible, so past the
+// first drip's shuffle the tail is 0 or 1.
+pub fn state_cycle<const M: usize>(sp: &SpritzN<M>, limit: u64) -> Option<Cycle> {
+	find_cycle(sp, |s| {
+		s.drip();
+	}, limit)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TraceFormat {
+	// per record the step as a little-endian u64, then i, j, k, z, a, w and
+	// the N bytes of S: 8 + 6 + N bytes, nothing between records.
+	Binary,
+	// a header line, then per record step,i,j,k,z,a,w,s0,..,s255.
+	Csv,
+}
+
+// drips steps bytes from sp, writing the state before the first and after
+// every every-th one. the output bytes aren't written; they're the z column.
+#[cfg(feature = "std")]
+pub fn trace<W: Write>(sp: &mut Spritz, steps: u64, every: u64, format: TraceFormat, mut out: W) -> io::Result<()> {
+	assert!(every > 0, "spritz: trace interval must be positive");
+	if format == TraceFormat::Csv {
+		write!(out, "step,i,j,k,z,a,w")?;
+		for n in 0 .. N {
+			write!(out, ",s{}", n)?;
+		}
+		writeln!(out)?;
+	}
+	for step in 0 ..= steps {
+		if step % every == 0 {
+			let regs = [sp.i, sp.j, sp.k, sp.z, sp.a, sp.w];
+			match format {
+				TraceFormat::Binary => {
+					out.write_all(&step.to_le_bytes())?;
+					out.write_all(&regs)?;
+					out.write_all(&sp.S)?;
+				}
+				TraceFormat::Csv => {
+					write!(out, "{}", step)?;
+					for v in regs.iter().chain(sp.S.iter()) {
+						write!(out, ",{}", v)?;
+					}
+					writeln!(out)?;
+				}
+			}
+		}
+		if step < steps {
+			sp.drip();
+		}
+	}
+	out.flush()
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn traces() {
+	let mut bin = vec![];
+	trace(&mut Spritz::new(b"ABC"), 10, 4, TraceFormat::Binary, &mut bin).unwrap();
+	let rec = 8 + 6 + N;
+	assert_eq!(bin.len(), 3 * rec);
+	assert_eq!(bin[rec .. rec + 8], 4u64.to_le_bytes());
+	let mut sp = Spritz::new(b"ABC");
+	let z = sp.squeeze(8)[7];
+	assert_eq!(bin[2 * rec + 11], z);
+	assert_eq!(bin[2 * rec + 14 ..], sp.S[..]);
+
+	let mut csv = vec![];
+	trace(&mut Spritz::new(b"ABC"), 10, 4, TraceFormat::Csv, &mut csv).unwrap();
+	let csv = String::from_utf8(csv).unwrap();
+	let lines: Vec<&str> = csv.lines().collect();
+	assert_eq!(lines.len(), 4);
+	assert!(lines[0].starts_with("step,i,j,k,z,a,w,s0,s1,"));
+	assert!(lines[1].starts_with("0,0,0,0,0,6,1,"));
+	assert_eq!(lines[3].split(',').count(), 7 + N);
+}
+
+#[test]
+fn cycles() {
+	// x^2 + 1 mod 255 from 3: 3, 10, 101, 2, 5, 26, 167, 95, 101, ..
+	assert_eq!(find_cycle(&3u32, |x| *x = (*x * *x + 1) % 255, 100), Some(Cycle { tail: 2, period: 6 }));
+	assert_eq!(find_cycle(&0u8, |x| *x = x.wrapping_add(1), 1000), Some(Cycle { tail: 0, period: 256 }));
+	assert_eq!(find_cycle(&0u8, |x| *x = x.wrapping_add(1), 300), None);
+	assert_eq!(state_cycle(&SpritzN::<16>::new(&[1, 2]), 10_000), None);
+}
+
+#[test]
+fn related_keys() {
+	let base = [7; 20];
+	let mut d1 = [0; 20];
+	d1[5] = 1;
+	let mut d2 = [0; 20];
+	d2[19] = 0x80;
+	let deltas: [&[u8]; 4] = [&d2, &[0; 20], &d1, &d2];
+	let ks = related_key_keystreams(&base, &deltas, 32).unwrap();
+	for (d, k) in deltas.iter().zip(ks.iter()) {
+		let key: Vec<u8> = base.iter().zip(d.iter()).map(|(b, d)| b ^ d).collect();
+		assert_eq!(k[..], Spritz::new(&key).squeeze(32)[..]);
+	}
+	assert_eq!(related_key_keystreams(&base, &[&[0; 3]], 8), Err(Error::InvalidLength));
+}
+
+#[test]
+fn weakened() {
+	let mut full = Weakened::new(b"arcfour", Rounds::FULL);
+	let mut sp = Spritz::new(b"arcfour");
+	full.absorb_stop();
+	sp.absorb_stop();
+	full.absorb(&[1; 100]);
+	sp.absorb(&[1; 100]);
+	let mut x = [0; 64];
+	full.squeeze_into(&mut x);
+	assert_eq!(x[..], sp.squeeze(64)[..]);
+	assert_eq!(full.state().usage(), sp.usage());
+
+	let mut weak = Weakened::new(b"arcfour", Rounds { passes: 0, whip_len: 1 });
+	let mut y = [0; 64];
+	weak.squeeze_into(&mut y);
+	assert!(x[..] != y[..]);
+	assert_eq!(weak.state().w(), 3);
+
+	let mut rc4 = Weakened::new(b"arcfour", Rounds::FULL).output(Output::Rc4);
+	let mut z = [0; 64];
+	rc4.squeeze_into(&mut z);
+	let mut sp = Spritz::new(b"arcfour");
+	let y = sp.squeeze(64);
+	assert_eq!(rc4.state().S(), sp.S());
+	assert!(z[..] != y[..]);
+}
+
+#[test]
+fn registers() {
+	let mut sp = Spritz::new(b"ABC");
+	assert_eq!((sp.a(), sp.w()), (6, 1));
+	let z = sp.drip();
+	assert_eq!((sp.z(), sp.a(), sp.w()), (z, 0, 7));
+	let mut seen = [false; N];
+	for v in sp.S().iter() {
+		seen[*v as usize] = true;
+	}
+	assert!(seen.iter().all(|v| *v));
+	let _ = (sp.i(), sp.j(), sp.k());
+}
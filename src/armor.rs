@@ -0,0 +1,96 @@
+// ASCII armor for sealed messages, laid out like OpenPGP's (RFC 4880 section
+// 6): base64 in 64 column lines, then "=" and the base64 of a CRC-24 of the
+// data, between BEGIN and END lines.
+//
+// the parser is strict: nothing outside the markers, every body line full
+// except the last, and the checksum must be present. \r\n line endings are
+// accepted since mail clients like to add them.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use encoding::{from_base64, to_base64};
+use error::{Error, Result};
+
+const BEGIN: &str = "-----BEGIN SPRITZ MESSAGE-----";
+const END: &str = "-----END SPRITZ MESSAGE-----";
+const LINE: usize = 64;
+
+fn crc24(data: &[u8]) -> u32 {
+	let mut crc: u32 = 0xb704ce;
+	for b in data {
+		crc ^= (*b as u32) << 16;
+		for _ in 0 .. 8 {
+			crc <<= 1;
+			if crc & 0x1000000 != 0 {
+				crc ^= 0x1864cfb;
+			}
+		}
+	}
+	crc & 0xffffff
+}
+
+fn crc_bytes(data: &[u8]) -> [u8; 3] {
+	let c = crc24(data);
+	[(c >> 16) as u8, (c >> 8) as u8, c as u8]
+}
+
+pub fn armor(data: &[u8]) -> String {
+	let b64 = to_base64(data);
+	let mut s = String::with_capacity(b64.len() + b64.len() / LINE + 80);
+	s.push_str(BEGIN);
+	s.push('\n');
+	for line in b64.as_bytes().chunks(LINE) {
+		s.push_str(::core::str::from_utf8(line).unwrap());
+		s.push('\n');
+	}
+	s.push('=');
+	s.push_str(&to_base64(&crc_bytes(data)));
+	s.push('\n');
+	s.push_str(END);
+	s.push('\n');
+	s
+}
+
+pub fn dearmor(s: &str) -> Result<Vec<u8>> {
+	let s = s.strip_suffix('\n').unwrap_or(s);
+	let lines: Vec<&str> = s.split('\n').map(|l| l.strip_suffix('\r').unwrap_or(l)).collect();
+	if lines.len() < 3 || lines[0] != BEGIN || lines[lines.len() - 1] != END {
+		return Err(Error::InvalidEncoding);
+	}
+	let crc = lines[lines.len() - 2];
+	let body = &lines[1 .. lines.len() - 2];
+	if !crc.starts_with('=') {
+		return Err(Error::InvalidEncoding);
+	}
+	for (i, l) in body.iter().enumerate() {
+		if l.len() > LINE || l.is_empty() || (i < body.len() - 1 && l.len() != LINE) {
+			return Err(Error::InvalidEncoding);
+		}
+	}
+	let data = from_base64(&body.concat())?;
+	if from_base64(&crc[1 ..])? != crc_bytes(&data) {
+		return Err(Error::BadChecksum);
+	}
+	Ok(data)
+}
+
+#[test]
+fn armor_roundtrip() {
+	assert_eq!(crc24(b"123456789"), 0x21cf02);
+
+	let data: Vec<u8> = (0 .. 200u32).map(|i| i as u8).collect();
+	let a = armor(&data);
+	assert!(a.starts_with("-----BEGIN SPRITZ MESSAGE-----\n"));
+	assert!(a.lines().all(|l| l.len() <= LINE || l.starts_with("-----")));
+	assert_eq!(dearmor(&a).unwrap(), data);
+	assert_eq!(dearmor(&a.replace('\n', "\r\n")).unwrap(), data);
+	assert_eq!(dearmor(&armor(b"")).unwrap(), b"");
+
+	let bad = a.replacen("AAEC", "AAED", 1);
+	assert_eq!(dearmor(&bad), Err(Error::BadChecksum));
+	assert_eq!(dearmor(&format!("junk\n{}", a)), Err(Error::InvalidEncoding));
+	let mut lines: Vec<&str> = a.lines().collect();
+	lines.remove(lines.len() - 2);
+	assert_eq!(dearmor(&lines.join("\n")), Err(Error::InvalidEncoding));
+}
@@ -0,0 +1,208 @@
+// encrypted, tamper-evident append-only log.
+//
+// the log starts with its id, a nonce the caller picks at random, and each
+// record is kind (1 byte) || length (4 bytes big endian) || sealed body.
+// record seq is sealed under chunk_nonce(id, seq), as chunk seq of a stream
+// with the id as its nonce, so logs under one key never share a nonce. kind,
+// sequence number and the previous record's tag are associated data, so
+// editing, dropping, reordering or splicing records breaks the chain.
+// close() writes an empty terminator record; a log that verifies without
+// one has lost its tail, unless its head matches one the caller saved from
+// append().
+
+use core::convert::TryFrom;
+use std::io::{self, Read, Write};
+
+use aead::{SpritzAead, Tag, TAG_LEN};
+use error::Error;
+use key::{Key, Nonce, NONCE_LEN};
+use stream::chunk_nonce;
+
+const KIND_RECORD: u8 = 0;
+const KIND_CLOSE: u8 = 1;
+
+// position in the chain: records written so far and the last tag.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LogHead {
+	pub id: Nonce,
+	pub seq: u64,
+	pub tag: Tag,
+}
+
+impl LogHead {
+	fn start(id: Nonce) -> LogHead {
+		LogHead { id, seq: 0, tag: Tag::new([0; TAG_LEN]) }
+	}
+
+	fn next(&self, tag: Tag) -> LogHead {
+		LogHead { id: self.id, seq: self.seq + 1, tag }
+	}
+
+	fn ad(&self, kind: u8) -> Vec<u8> {
+		let mut ad = Vec::with_capacity(1 + 8 + TAG_LEN);
+		ad.push(kind);
+		ad.extend_from_slice(&self.seq.to_be_bytes());
+		ad.extend_from_slice(self.tag.as_bytes());
+		ad
+	}
+}
+
+pub struct AuthLog<W: Write> {
+	w: W,
+	aead: SpritzAead,
+	head: LogHead,
+}
+
+impl<W: Write> AuthLog<W> {
+	// writes the id straight away. it must be random, e.g. from
+	// Nonce::generate, and not reused for another log under key.
+	pub fn new(mut w: W, key: &Key, id: &Nonce) -> io::Result<AuthLog<W>> {
+		w.write_all(id.as_bytes())?;
+		Ok(AuthLog::resume(w, key, LogHead::start(*id)))
+	}
+
+	// keep appending to a log whose head came from verify().
+	pub fn resume(w: W, key: &Key, head: LogHead) -> AuthLog<W> {
+		AuthLog { w, aead: SpritzAead::new(key), head }
+	}
+
+	pub fn head(&self) -> LogHead {
+		self.head
+	}
+
+	fn write(&mut self, kind: u8, record: &[u8]) -> io::Result<LogHead> {
+		if record.len() + TAG_LEN > u32::MAX as usize {
+			return Err(io::Error::new(io::ErrorKind::InvalidInput, "spritz: log record too large"));
+		}
+		let mut body = record.to_vec();
+		let nonce = chunk_nonce(&self.head.id, self.head.seq);
+		let tag = self.aead.seal_in_place_detached(&nonce, &self.head.ad(kind), &mut body);
+		self.w.write_all(&[kind])?;
+		self.w.write_all(&((body.len() + TAG_LEN) as u32).to_be_bytes())?;
+		self.w.write_all(&body)?;
+		self.w.write_all(tag.as_bytes())?;
+		self.head = self.head.next(tag);
+		Ok(self.head)
+	}
+
+	pub fn append(&mut self, record: &[u8]) -> io::Result<LogHead> {
+		self.write(KIND_RECORD, record)
+	}
+
+	pub fn close(mut self) -> io::Result<W> {
+		self.write(KIND_CLOSE, &[])?;
+		self.w.flush()?;
+		Ok(self.w)
+	}
+}
+
+pub struct VerifiedLog {
+	pub records: Vec<Vec<u8>>,
+	pub head: LogHead,
+	pub closed: bool,
+}
+
+fn read_exact_or_truncated<R: Read>(r: &mut R, buf: &mut [u8]) -> io::Result<()> {
+	r.read_exact(buf).map_err(|e| {
+		if e.kind() == io::ErrorKind::UnexpectedEof {
+			Error::Truncated.into()
+		} else {
+			e
+		}
+	})
+}
+
+// decrypt and check the whole chain. errors are io::Errors wrapping an
+// Error for anything that fails verification.
+pub fn verify<R: Read>(mut r: R, key: &Key) -> io::Result<VerifiedLog> {
+	let aead = SpritzAead::new(key);
+	let mut id = [0; NONCE_LEN];
+	read_exact_or_truncated(&mut r, &mut id)?;
+	let mut log = VerifiedLog { records: vec![], head: LogHead::start(Nonce::new(id)), closed: false };
+	loop {
+		let mut kind = [0; 1];
+		match r.read(&mut kind) {
+			Ok(0) => return Ok(log),
+			Ok(_) => {}
+			Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+			Err(e) => return Err(e),
+		}
+		if log.closed {
+			return Err(Error::InvalidLength.into());
+		}
+		let mut len = [0; 4];
+		read_exact_or_truncated(&mut r, &mut len)?;
+		// the length isn't authenticated yet, so the body grows as it's
+		// read rather than being allocated up front.
+		let len = u32::from_be_bytes(len) as usize;
+		let mut body = vec![];
+		(&mut r).take(len as u64).read_to_end(&mut body)?;
+		if body.len() < len {
+			return Err(Error::Truncated.into());
+		}
+
+		let nonce = chunk_nonce(&log.head.id, log.head.seq);
+		let pt = aead.open(&nonce, &log.head.ad(kind[0]), &body)?;
+		let tag = Tag::try_from(&body[body.len() - TAG_LEN ..])?;
+		log.head = log.head.next(tag);
+		if kind[0] == KIND_CLOSE {
+			log.closed = true;
+		} else {
+			log.records.push(pt);
+		}
+	}
+}
+
+#[test]
+fn authlog() {
+	let key = Key::new([3; 32]);
+	let id = Nonce::new([9; NONCE_LEN]);
+	let mut log = AuthLog::new(vec![], &key, &id).unwrap();
+	log.append(b"first").unwrap();
+	let mid = log.append(b"second").unwrap();
+	log.append(b"third").unwrap();
+	let buf = log.close().unwrap();
+
+	let v = verify(&buf[..], &key).unwrap();
+	assert!(v.closed);
+	assert_eq!(v.records, [&b"first"[..], b"second", b"third"]);
+	assert_eq!(v.head.id, id);
+
+	let err = |buf: &[u8]| {
+		let e = verify(buf, &key).err().unwrap();
+		*e.get_ref().unwrap().downcast_ref::<Error>().unwrap()
+	};
+
+	let mut bad = buf.clone();
+	bad[NONCE_LEN + 6] ^= 1;
+	assert_eq!(err(&bad), Error::InvalidTag);
+	assert_eq!(err(&buf[.. buf.len() - 1]), Error::Truncated);
+	assert_eq!(err(&buf[.. NONCE_LEN - 1]), Error::Truncated);
+	// a length far past the end of the file.
+	let mut long = id.as_bytes().to_vec();
+	long.extend_from_slice(&[KIND_RECORD, 0xff, 0xff, 0xff, 0xff, 1, 2, 3]);
+	assert_eq!(err(&long), Error::Truncated);
+
+	// drop the first record.
+	let first = 5 + 5 + TAG_LEN;
+	let mut dropped = id.as_bytes().to_vec();
+	dropped.extend_from_slice(&buf[NONCE_LEN + first ..]);
+	assert_eq!(err(&dropped), Error::InvalidTag);
+
+	// cut at a record boundary: verifies, but isn't closed and the head
+	// doesn't match.
+	let v = verify(&buf[.. NONCE_LEN + 2 * first + 1], &key).unwrap();
+	assert!(!v.closed);
+	assert_eq!(v.head, mid);
+	assert_eq!(v.records.len(), 2);
+
+	// another log under the same key seals the same records differently,
+	// and its records can't be spliced into this one.
+	let mut other = AuthLog::new(vec![], &key, &Nonce::new([8; NONCE_LEN])).unwrap();
+	other.append(b"first").unwrap();
+	let other = other.close().unwrap();
+	assert!(other[NONCE_LEN ..][.. first] != buf[NONCE_LEN ..][.. first]);
+	let mut spliced = id.as_bytes().to_vec();
+	spliced.extend_from_slice(&other[NONCE_LEN ..]);
+	assert_eq!(err(&spliced), Error::InvalidTag);
+}
@@ -0,0 +1,1015 @@
+// the spritz command line tool.
+//
+// encrypted files are a random nonce (16 bytes) followed by the chunked
+// stream format from spritz::stream, so they can be piped and are checked
+// chunk by chunk as they are decrypted. keys are 32 bytes, given as a file
+// holding them raw or in hex, as hex in an environment variable, or typed
+// in as hex when neither is given; --passphrase derives one with the pbkdf.
+//
+// it builds for wasm32-wasi, where everything goes through stdin, stdout
+// and preopened files: there's no terminal to prompt on, so keys must come
+// from an option, and the OS seed is WASI's random_get.
+
+extern crate spritz;
+
+use std::convert::TryFrom;
+use std::env;
+use std::fmt::Display;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+#[cfg(not(target_os = "wasi"))]
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use spritz::encoding::{from_hex, to_base64, to_hex};
+use spritz::arduino;
+use spritz::kdf::{calibrate, MAX_ITERATIONS, MIN_ITERATIONS, SALT_LEN};
+use spritz::stream::DEFAULT_CHUNK_SIZE;
+use spritz::{copy_decrypt, copy_encrypt, pbkdf, DecryptReader, EncryptWriter, Key, SpritzAead, KeystreamReader, Nonce, OutputFile, ProgressReader, Spritz, SpritzHasher, SpritzMac, SpritzRng, Tag, XorReader, XorWriter};
+
+const USAGE: &str = "usage: spritz <command> [options]
+
+commands:
+  encrypt [key] [-o out] [in]    encrypt in (or stdin) to out (or stdout)
+  decrypt [key] [-o out] [in]    decrypt and verify
+    --passphrase                 derive the key from a passphrase instead
+    --quiet                      no progress display
+    --format <f>                 native, or raw/raw-mac/arduino for other tools
+    --nonce <hex>                iv for --format raw or raw-mac
+  hash [-r] [file...]            print digests like sha256sum
+  hash -c [file...]              check digests listed in the files
+  mac [key] [file]               print the file's authentication tag
+  verify [key] --tag <hex> [file]  exit nonzero unless the tag matches
+  keygen <path>                  write a new random key, readable only by you
+  fingerprint [key] [keyfile]    print a key's fingerprint, as words and randomart
+  random --bytes <n> [--hex|--base64] [--seed <s>]
+                                 random bytes, raw unless encoded
+  random --password [--length <n>] [--charset <chars>] [--seed <s>]
+  keystream --key <hex> [--nonce <hex>] [--bytes <n>]
+                                 raw keystream, endless without --bytes
+  kat [--out <path>]             known-answer test vectors as json
+  archive create [key] <archive> <path...>
+  archive extract [key] [-C <dir>] <archive>
+  archive list [key] <archive>   an encrypted bundle of files, like tar
+
+key options:
+  --key <hex>          on the command line (visible to other users)
+  --key-file <path>    32 raw bytes or 64 hex digits
+  --key-env <var>      hex in an environment variable
+  (otherwise the key is read from the terminal)
+";
+
+type CmdResult = Result<(), String>;
+
+fn main() {
+	let argv: Vec<String> = env::args().skip(1).collect();
+	let (cmd, rest) = match argv.split_first() {
+		Some((c, r)) => (c.as_str(), r),
+		None => usage(),
+	};
+	let r = match cmd {
+		"encrypt" => encrypt(rest),
+		"decrypt" => decrypt(rest),
+		"hash" => hash(rest),
+		"mac" => mac(rest),
+		"verify" => verify(rest),
+		"keygen" => keygen(rest),
+		"fingerprint" => fingerprint(rest),
+		"random" => random(rest),
+		"keystream" => keystream(rest),
+		"kat" => kat(rest),
+		"archive" => archive(rest),
+		"help" | "-h" | "--help" => {
+			print!("{}", USAGE);
+			Ok(())
+		}
+		_ => usage(),
+	};
+	if let Err(e) = r {
+		eprintln!("spritz: {}", e.trim_start_matches("spritz: "));
+		process::exit(1);
+	}
+}
+
+fn usage() -> ! {
+	eprint!("{}", USAGE);
+	process::exit(2);
+}
+
+// "context: error", for errors from the library and the os.
+fn fail<E: Display>(context: &str, e: E) -> String {
+	format!("{}: {}", context, e.to_string().trim_start_matches("spritz: "))
+}
+
+// long options only. those named in takes_value consume the next argument
+// (or the part after '='); "-" and anything after "--" are operands.
+struct Args {
+	opts: Vec<(String, String)>,
+	switches: Vec<String>,
+	free: Vec<String>,
+}
+
+impl Args {
+	fn parse(argv: &[String], takes_value: &[&str], switches: &[&str]) -> Result<Args, String> {
+		let mut a = Args { opts: vec![], switches: vec![], free: vec![] };
+		let mut it = argv.iter();
+		while let Some(arg) = it.next() {
+			if arg == "--" {
+				a.free.extend(it.cloned());
+				break;
+			}
+			if !arg.starts_with('-') || arg == "-" {
+				a.free.push(arg.clone());
+				continue;
+			}
+			let (name, inline) = match arg.find('=') {
+				Some(i) => (&arg[.. i], Some(arg[i + 1 ..].to_string())),
+				None => (arg.as_str(), None),
+			};
+			if takes_value.contains(&name) {
+				let v = match inline {
+					Some(v) => v,
+					None => it.next().ok_or_else(|| format!("{} needs a value", name))?.clone(),
+				};
+				a.opts.push((name.to_string(), v));
+			} else if switches.contains(&name) && inline.is_none() {
+				a.switches.push(name.to_string());
+			} else {
+				return Err(format!("unknown option {}", arg));
+			}
+		}
+		Ok(a)
+	}
+
+	// the last one wins, as with most tools.
+	fn get(&self, name: &str) -> Option<&str> {
+		self.opts.iter().rev().find(|o| o.0 == name).map(|o| o.1.as_str())
+	}
+
+	fn has(&self, name: &str) -> bool {
+		self.switches.iter().any(|s| s == name)
+	}
+
+	// at most one operand, "-" meaning stdin/stdout.
+	fn operand(&self) -> Result<Option<&str>, String> {
+		match self.free.len() {
+			0 => Ok(None),
+			1 if self.free[0] == "-" => Ok(None),
+			1 => Ok(Some(&self.free[0])),
+			_ => Err("too many arguments".to_string()),
+		}
+	}
+}
+
+const KEY_OPTS: [&str; 3] = ["--key", "--key-file", "--key-env"];
+
+// the key options plus a command's own.
+fn with_key_opts<'a>(extra: &[&'a str]) -> Vec<&'a str> {
+	KEY_OPTS.iter().chain(extra.iter()).cloned().collect()
+}
+
+fn parse_key(data: &[u8]) -> Result<Key, String> {
+	if data.len() == 32 {
+		return Ok(Key::try_from(data).unwrap());
+	}
+	let s = String::from_utf8_lossy(data);
+	let b = from_hex(s.trim()).map_err(|_| "key must be 32 bytes or 64 hex digits".to_string())?;
+	Key::try_from(&b[..]).map_err(|e| fail("key", e))
+}
+
+fn read_key(a: &Args) -> Result<Key, String> {
+	if let Some(hex) = a.get("--key") {
+		return parse_key(hex.as_bytes());
+	}
+	if let Some(path) = a.get("--key-file") {
+		return parse_key(&fs::read(path).map_err(|e| fail(path, e))?);
+	}
+	if let Some(var) = a.get("--key-env") {
+		let v = env::var(var).map_err(|e| fail(var, e))?;
+		return parse_key(v.as_bytes());
+	}
+	parse_key(prompt("key (hex): ")?.as_bytes())
+}
+
+// read a line from the terminal with echo off. stty does the switching, so
+// this needs a unix terminal.
+#[cfg(not(target_os = "wasi"))]
+fn prompt(msg: &str) -> Result<String, String> {
+	let tty = OpenOptions::new().read(true).write(true).open("/dev/tty").map_err(|e| fail("/dev/tty", e))?;
+	let stty = |arg: &str| {
+		let tty_in = match File::open("/dev/tty") {
+			Ok(f) => f,
+			Err(_) => return false,
+		};
+		Command::new("stty").arg(arg).stdin(tty_in).stderr(Stdio::null()).status().map(|s| s.success()).unwrap_or(false)
+	};
+	(&tty).write_all(msg.as_bytes()).map_err(|e| fail("/dev/tty", e))?;
+	let quiet = stty("-echo");
+	let mut line = String::new();
+	let r = BufReader::new(&tty).read_line(&mut line);
+	if quiet {
+		stty("echo");
+		let _ = (&tty).write_all(b"\n");
+	}
+	r.map_err(|e| fail("/dev/tty", e))?;
+	Ok(line.trim_end_matches(&['\r', '\n'][..]).to_string())
+}
+
+// stdin is the data, and there's no tty or stty to switch echo with.
+#[cfg(target_os = "wasi")]
+fn prompt(_: &str) -> Result<String, String> {
+	Err("no terminal to prompt on under WASI; give the key with --key, --key-file or --key-env".to_string())
+}
+
+fn open_input(path: Option<&str>) -> Result<Box<dyn Read>, String> {
+	match path {
+		None => Ok(Box::new(io::stdin())),
+		Some(p) => Ok(Box::new(File::open(p).map_err(|e| fail(p, e))?)),
+	}
+}
+
+enum Sink {
+	Stdout(io::Stdout),
+	File(OutputFile),
+}
+
+impl Write for Sink {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		match self {
+			Sink::Stdout(s) => s.write(buf),
+			Sink::File(f) => f.write(buf),
+		}
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		match self {
+			Sink::Stdout(s) => s.flush(),
+			Sink::File(f) => f.flush(),
+		}
+	}
+}
+
+// a named output is an OutputFile, renamed into place by finish(), so it is
+// either complete or absent. if the command fails the temporary file is
+// removed, and a failed decryption leaves no partial plaintext behind.
+struct Output {
+	w: Sink,
+	name: String,
+}
+
+impl Output {
+	fn create(path: Option<&str>) -> Result<Output, String> {
+		let w = match path {
+			None => Sink::Stdout(io::stdout()),
+			Some(p) => Sink::File(OutputFile::create(p).map_err(|e| fail(p, e))?),
+		};
+		Ok(Output { w, name: path.unwrap_or("stdout").to_string() })
+	}
+
+	fn name(&self) -> &str {
+		&self.name
+	}
+
+	fn finish(self) -> CmdResult {
+		let Output { w, name } = self;
+		let r = match w {
+			Sink::Stdout(mut s) => s.flush(),
+			Sink::File(f) => f.commit(),
+		};
+		r.map_err(|e| fail(&name, e))
+	}
+}
+
+// drawn on stderr, and only when that is a terminal.
+struct Bar {
+	on: bool,
+	drawn: Option<Instant>,
+}
+
+impl Bar {
+	fn new(quiet: bool) -> Bar {
+		Bar { on: !quiet && io::stderr().is_terminal(), drawn: None }
+	}
+
+	fn update(&mut self, done: u64, total: Option<u64>) {
+		if !self.on || self.drawn.is_some_and(|t| t.elapsed() < Duration::from_millis(100)) {
+			return;
+		}
+		let mib = done as f64 / (1 << 20) as f64;
+		match total {
+			Some(t) if t > 0 => eprint!("\r{:3}% {:.1} MiB", done * 100 / t, mib),
+			_ => eprint!("\r{:.1} MiB", mib),
+		}
+		self.drawn = Some(Instant::now());
+	}
+
+	fn finish(&mut self, done: u64, total: Option<u64>) {
+		if self.drawn.is_some() {
+			self.drawn = None;
+			self.update(done, total);
+			eprintln!();
+		}
+	}
+}
+
+fn input_len(path: Option<&str>) -> Option<u64> {
+	path.and_then(|p| fs::metadata(p).ok()).filter(|m| m.is_file()).map(|m| m.len())
+}
+
+// passphrase-encrypted files start with the salt and iteration count
+// instead, and the rest is as for a key.
+const PASSPHRASE_TIME: Duration = Duration::from_secs(1);
+const KDF_HEADER_LEN: usize = SALT_LEN + 4;
+
+// the count isn't authenticated until the key it gives has opened the
+// file, so one out of range is refused before the pbkdf runs on it.
+fn stored_iterations(name: &str, h: &[u8; KDF_HEADER_LEN]) -> Result<u32, String> {
+	let mut b = [0; 4];
+	b.copy_from_slice(&h[SALT_LEN ..]);
+	let n = u32::from_be_bytes(b);
+	if !(MIN_ITERATIONS ..= MAX_ITERATIONS).contains(&n) {
+		return Err(format!("{}: iteration count {} is out of range", name, n));
+	}
+	Ok(n)
+}
+
+const CRYPT_OPTS: [&str; 4] = ["-o", "--output", "--format", "--nonce"];
+const CRYPT_SWITCHES: [&str; 2] = ["--passphrase", "--quiet"];
+
+// --format picks a layout other implementations can read. only raw-mac is
+// authenticated, and only once the whole file has been read.
+//
+//   raw      the message xored with the keystream, nothing else. the
+//            nonce, if any, is passed with --nonce and absorbed as an iv.
+//   raw-mac  raw with a 32 byte SpritzMac tag over the ciphertext after it,
+//            under a key derived from the cipher key.
+//   arduino  a random 16 byte iv and then the output of spritz_crypt after
+//            spritz_setupIV(key, iv), as the Arduino SpritzCipher library
+//            would produce it.
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+	Native,
+	Raw,
+	RawMac,
+	Arduino,
+}
+
+const RAW_MAC_LABEL: &[u8] = b"spritz raw-mac trailer";
+
+fn parse_format(a: &Args) -> Result<Format, String> {
+	let f = match a.get("--format").unwrap_or("native") {
+		"native" => Format::Native,
+		"raw" => Format::Raw,
+		"raw-mac" => Format::RawMac,
+		"arduino" => Format::Arduino,
+		f => return Err(format!("unknown format {} (native, raw, raw-mac or arduino)", f)),
+	};
+	if f != Format::Raw && f != Format::RawMac && a.get("--nonce").is_some() {
+		return Err("--nonce is only for --format raw or raw-mac".to_string());
+	}
+	if f != Format::Native && a.has("--passphrase") {
+		return Err("--passphrase is only for the native format".to_string());
+	}
+	Ok(f)
+}
+
+// the same in both directions, apart from where the arduino iv comes from.
+fn crypt_xor(a: &Args, format: Format, encrypting: bool) -> CmdResult {
+	let key = read_key(a)?;
+	let input = a.operand()?;
+	let name = input.unwrap_or("stdin");
+	let mut r = open_input(input)?;
+	let mut out = Output::create(a.get("-o").or_else(|| a.get("--output")))?;
+	let e = match (format, a.get("--nonce")) {
+		(Format::Raw | Format::RawMac, Some(n)) => Spritz::with_key(&key).iv(&from_hex(n).map_err(|e| fail("--nonce", e))?),
+		(Format::Raw | Format::RawMac, None) => Spritz::with_key(&key).deterministic(),
+		_ => {
+			let mut iv = [0; 16];
+			if encrypting {
+				SpritzRng::from_os().map_err(|e| fail("/dev/urandom", e))?.fill_bytes(&mut iv);
+				out.w.write_all(&iv).map_err(|e| fail(out.name(), e))?;
+			} else {
+				r.read_exact(&mut iv).map_err(|_| format!("{}: too short for an iv", name))?;
+			}
+			arduino::setup_iv(key.as_bytes(), &iv)
+		}
+	};
+	let mac_key: [u8; 32] = Spritz::mac_array(key.as_bytes(), RAW_MAC_LABEL);
+	let out_name = out.name().to_string();
+	if encrypting {
+		let mut w = match format {
+			Format::RawMac => XorWriter::with_mac(&mut out.w, e, &mac_key),
+			_ => XorWriter::new(&mut out.w, e),
+		};
+		copy_named(&mut r, name, &mut w, &out_name)?;
+		w.finish().map_err(|e| fail(&out_name, e))?;
+	} else {
+		let mut r = match format {
+			Format::RawMac => XorReader::with_mac(r, e, &mac_key),
+			_ => XorReader::new(r, e),
+		};
+		copy_named(&mut r, name, &mut out.w, &out_name)?;
+	}
+	out.finish()
+}
+
+// io::copy, but with errors put down to the right side.
+fn copy_named<R: Read, W: Write>(r: &mut R, rname: &str, w: &mut W, wname: &str) -> CmdResult {
+	let mut buf = vec![0; DEFAULT_CHUNK_SIZE];
+	loop {
+		let n = match r.read(&mut buf) {
+			Ok(0) => return Ok(()),
+			Ok(n) => n,
+			Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+			Err(e) => return Err(fail(rname, e)),
+		};
+		w.write_all(&buf[.. n]).map_err(|e| fail(wname, e))?;
+	}
+}
+
+fn encrypt(argv: &[String]) -> CmdResult {
+	let a = Args::parse(argv, &with_key_opts(&CRYPT_OPTS), &CRYPT_SWITCHES)?;
+	let format = parse_format(&a)?;
+	if format != Format::Native {
+		return crypt_xor(&a, format, true);
+	}
+	let mut rng = SpritzRng::from_os().map_err(|e| fail("/dev/urandom", e))?;
+	let mut header = vec![];
+	let key = if a.has("--passphrase") {
+		let p = prompt("passphrase: ")?;
+		if p != prompt("again: ")? {
+			return Err("passphrases don't match".to_string());
+		}
+		let mut salt = [0; SALT_LEN];
+		rng.fill_bytes(&mut salt);
+		let iterations = calibrate(PASSPHRASE_TIME);
+		header.extend_from_slice(&salt);
+		header.extend_from_slice(&iterations.to_be_bytes());
+		pbkdf(p.as_bytes(), &salt, iterations)
+	} else {
+		read_key(&a)?
+	};
+	let input = a.operand()?;
+	let total = input_len(input);
+	let mut out = Output::create(a.get("-o").or_else(|| a.get("--output")))?;
+	let nonce = Nonce::generate(&mut rng);
+	header.extend_from_slice(nonce.as_bytes());
+	out.w.write_all(&header).map_err(|e| fail(out.name(), e))?;
+	let mut bar = Bar::new(a.has("--quiet"));
+	let mut r = ProgressReader::new(open_input(input)?, total, |d, t| bar.update(d, t));
+	copy_encrypt(&mut r, &mut out.w, &key, &nonce).map_err(|e| fail(input.unwrap_or("stdin"), e))?;
+	let done = r.done();
+	bar.finish(done, total);
+	out.finish()
+}
+
+fn decrypt(argv: &[String]) -> CmdResult {
+	let a = Args::parse(argv, &with_key_opts(&CRYPT_OPTS), &CRYPT_SWITCHES)?;
+	let format = parse_format(&a)?;
+	if format != Format::Native {
+		return crypt_xor(&a, format, false);
+	}
+	let input = a.operand()?;
+	let name = input.unwrap_or("stdin");
+	let total = input_len(input);
+	let mut r = open_input(input)?;
+	let not_spritz = |_| format!("{}: not a spritz file", name);
+	let key = if a.has("--passphrase") {
+		let mut h = [0; KDF_HEADER_LEN];
+		r.read_exact(&mut h).map_err(not_spritz)?;
+		let iterations = stored_iterations(name, &h)?;
+		pbkdf(prompt("passphrase: ")?.as_bytes(), &h[.. SALT_LEN], iterations)
+	} else {
+		read_key(&a)?
+	};
+	let mut n = [0; 16];
+	r.read_exact(&mut n).map_err(not_spritz)?;
+	let mut out = Output::create(a.get("-o").or_else(|| a.get("--output")))?;
+	let mut bar = Bar::new(a.has("--quiet"));
+	let mut r = ProgressReader::new(r, total, |d, t| bar.update(d, t));
+	copy_decrypt(&mut r, &mut out.w, &key, &Nonce::new(n)).map_err(|e| fail(name, e))?;
+	let done = r.done();
+	bar.finish(done, total);
+	out.finish()
+}
+
+fn hash_reader<R: Read>(mut r: R) -> io::Result<String> {
+	let mut h = SpritzHasher::new();
+	io::copy(&mut r, &mut h)?;
+	Ok(to_hex(h.finalize().as_bytes()))
+}
+
+fn hash_path(path: &str) -> Result<String, String> {
+	if path == "-" {
+		return hash_reader(io::stdin().lock()).map_err(|e| fail("stdin", e));
+	}
+	hash_reader(File::open(path).map_err(|e| fail(path, e))?).map_err(|e| fail(path, e))
+}
+
+// files under dir, sorted so the output is stable. like find -type f it
+// leaves out symbolic links, which could lead back up the tree, and
+// anything else that isn't a file or directory.
+fn walk(dir: &Path, out: &mut Vec<String>) -> CmdResult {
+	let d = dir.display().to_string();
+	let mut entries: Vec<_> = fs::read_dir(dir).map_err(|e| fail(&d, e))?.collect::<io::Result<_>>().map_err(|e| fail(&d, e))?;
+	entries.sort_by_key(|e| e.file_name());
+	for e in entries {
+		let p = e.path();
+		let t = e.file_type().map_err(|e| fail(&p.display().to_string(), e))?;
+		if t.is_dir() {
+			walk(&p, out)?;
+		} else if t.is_file() {
+			out.push(p.display().to_string());
+		}
+	}
+	Ok(())
+}
+
+// lines are "<hex>  <path>", as sha256sum writes them. with -c, the
+// operands are lists in that format and every file in them is checked.
+fn hash(argv: &[String]) -> CmdResult {
+	let a = Args::parse(argv, &[], &["-c", "--check", "-r", "--recursive"])?;
+	let mut files = a.free.clone();
+	if files.is_empty() {
+		files.push("-".to_string());
+	}
+	if a.has("-c") || a.has("--check") {
+		return check(&files);
+	}
+	let mut paths = vec![];
+	for f in files {
+		if (a.has("-r") || a.has("--recursive")) && Path::new(&f).is_dir() {
+			walk(Path::new(&f), &mut paths)?;
+		} else {
+			paths.push(f);
+		}
+	}
+	let stdout = io::stdout();
+	let mut out = stdout.lock();
+	for p in paths {
+		writeln!(out, "{}  {}", hash_path(&p)?, p).map_err(|e| fail("stdout", e))?;
+	}
+	Ok(())
+}
+
+fn check(lists: &[String]) -> CmdResult {
+	let (mut bad, mut unreadable) = (0, 0);
+	for list in lists {
+		let r: Box<dyn BufRead> = match list.as_str() {
+			"-" => Box::new(BufReader::new(io::stdin())),
+			l => Box::new(BufReader::new(File::open(l).map_err(|e| fail(l, e))?)),
+		};
+		for line in r.lines() {
+			let line = line.map_err(|e| fail(list, e))?;
+			if line.is_empty() {
+				continue;
+			}
+			let (want, path) = match (line.get(.. 64), line.get(64 .. 66), line.get(66 ..)) {
+				(Some(d), Some("  "), Some(p)) | (Some(d), Some(" *"), Some(p)) => (d.to_lowercase(), p),
+				_ => return Err(format!("{}: improperly formatted line: {}", list, line)),
+			};
+			match hash_path(path) {
+				Ok(got) if got == want => println!("{}: OK", path),
+				Ok(_) => {
+					println!("{}: FAILED", path);
+					bad += 1;
+				}
+				Err(e) => {
+					eprintln!("spritz: {}", e);
+					println!("{}: FAILED open or read", path);
+					unreadable += 1;
+				}
+			}
+		}
+	}
+	match (bad, unreadable) {
+		(0, 0) => Ok(()),
+		_ => Err(format!("{} computed checksums did NOT match, {} files could not be read", bad, unreadable)),
+	}
+}
+
+fn mac_input(key: &Key, path: Option<&str>) -> Result<SpritzMac, String> {
+	let name = path.unwrap_or("stdin");
+	let mut m = SpritzMac::new(key.as_bytes());
+	io::copy(&mut open_input(path)?, &mut m).map_err(|e| fail(name, e))?;
+	Ok(m)
+}
+
+fn mac(argv: &[String]) -> CmdResult {
+	let a = Args::parse(argv, &KEY_OPTS, &[])?;
+	let key = read_key(&a)?;
+	let path = a.operand()?;
+	let tag = mac_input(&key, path)?.finalize();
+	println!("{}  {}", to_hex(tag.as_bytes()), path.unwrap_or("-"));
+	Ok(())
+}
+
+fn verify(argv: &[String]) -> CmdResult {
+	let a = Args::parse(argv, &with_key_opts(&["--tag"]), &[])?;
+	let tag = Tag::from_hex(a.get("--tag").ok_or("--tag is required")?).map_err(|e| fail("--tag", e))?;
+	let key = read_key(&a)?;
+	let path = a.operand()?;
+	mac_input(&key, path)?.verify(&tag).map_err(|e| fail(path.unwrap_or("stdin"), e))
+}
+
+// hex, so the file can also be pasted into --key or an environment variable.
+// an existing file is never overwritten.
+fn keygen(argv: &[String]) -> CmdResult {
+	let a = Args::parse(argv, &[], &[])?;
+	let path = a.operand()?.ok_or("keygen needs a path")?;
+	let key = Key::generate(&mut SpritzRng::from_os().map_err(|e| fail("/dev/urandom", e))?);
+	let mut o = OpenOptions::new();
+	o.write(true).create_new(true);
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::OpenOptionsExt;
+		o.mode(0o600);
+	}
+	let mut f = o.open(path).map_err(|e| fail(path, e))?;
+	writeln!(f, "{}", to_hex(key.as_bytes())).map_err(|e| fail(path, e))?;
+	println!("{}", key.fingerprint());
+	Ok(())
+}
+
+fn fingerprint(argv: &[String]) -> CmdResult {
+	let a = Args::parse(argv, &KEY_OPTS, &[])?;
+	let key = match a.operand()? {
+		Some(path) => parse_key(&fs::read(path).map_err(|e| fail(path, e))?)?,
+		None => read_key(&a)?,
+	};
+	let fp = key.fingerprint();
+	println!("{}\n{}\n{}", fp, fp.to_words(), fp.randomart());
+	Ok(())
+}
+
+const PASSWORD_CHARSET: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+fn parse_count(a: &Args, name: &str, default: Option<usize>) -> Result<usize, String> {
+	match a.get(name) {
+		Some(v) => v.parse().map_err(|_| format!("{}: not a number: {}", name, v)),
+		None => default.ok_or_else(|| format!("{} is required", name)),
+	}
+}
+
+// chars drawn uniformly: values past the last whole multiple of the charset
+// size are rejected rather than folded in with %.
+fn password(rng: &mut SpritzRng, charset: &[char], len: usize) -> String {
+	let n = charset.len() as u64;
+	let limit = (1 << 32) - (1 << 32) % n;
+	let mut s = String::new();
+	while s.chars().count() < len {
+		let v = rng.next_u32() as u64;
+		if v < limit {
+			s.push(charset[(v % n) as usize]);
+		}
+	}
+	s
+}
+
+// --seed makes the output reproducible, for tests; without it the generator
+// is seeded from the os.
+fn random(argv: &[String]) -> CmdResult {
+	let a = Args::parse(argv, &["--bytes", "--length", "--charset", "--seed"], &["--hex", "--base64", "--password"])?;
+	a.operand()?.map_or(Ok(()), |x| Err(format!("unexpected argument {}", x)))?;
+	let mut rng = match a.get("--seed") {
+		Some(seed) => SpritzRng::new(seed.as_bytes()),
+		None => SpritzRng::from_os().map_err(|e| fail("/dev/urandom", e))?,
+	};
+	if a.has("--password") {
+		let charset: Vec<char> = a.get("--charset").unwrap_or(PASSWORD_CHARSET).chars().collect();
+		if charset.is_empty() {
+			return Err("--charset is empty".to_string());
+		}
+		println!("{}", password(&mut rng, &charset, parse_count(&a, "--length", Some(24))?));
+		return Ok(());
+	}
+	let mut buf = vec![0; parse_count(&a, "--bytes", None)?];
+	rng.fill_bytes(&mut buf);
+	let r = if a.has("--hex") {
+		writeln!(io::stdout(), "{}", to_hex(&buf))
+	} else if a.has("--base64") {
+		writeln!(io::stdout(), "{}", to_base64(&buf))
+	} else {
+		io::stdout().write_all(&buf)
+	};
+	r.map_err(|e| fail("stdout", e))
+}
+
+// the bare cipher, for statistical test suites and for comparing against
+// other implementations: the key (any length, in hex) is absorbed, then the
+// nonce after a stop if one is given, as spritz_setupIV does.
+fn keystream(argv: &[String]) -> CmdResult {
+	let a = Args::parse(argv, &["--key", "--nonce", "--bytes"], &[])?;
+	let key = from_hex(a.get("--key").ok_or("--key is required")?).map_err(|e| fail("--key", e))?;
+	let e = match a.get("--nonce") {
+		Some(n) => Spritz::with_raw_key(&key).iv(&from_hex(n).map_err(|e| fail("--nonce", e))?),
+		None => Spritz::with_raw_key(&key).deterministic(),
+	};
+	let stdout = io::stdout();
+	let mut out = stdout.lock();
+	let r = match a.get("--bytes") {
+		Some(_) => io::copy(&mut KeystreamReader::new(e).take(parse_count(&a, "--bytes", None)? as u64), &mut out),
+		None => io::copy(&mut KeystreamReader::new(e), &mut out),
+	};
+	match r {
+		Err(ref e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+		r => r.map(|_| ()).map_err(|e| fail("stdout", e)),
+	}
+}
+
+// known-answer vectors for checking other implementations. all byte
+// strings are lowercase hex:
+//
+//   {
+//     "schema": "spritz-kat/1",
+//     "keystream": [{"key", "iv" (or null), "output"}],
+//         output after Spritz::with_raw_key(key).deterministic(), or
+//         .iv(iv) if iv is given
+//     "hash": [{"message", "digest"}],      hash256, 32 bytes
+//     "mac": [{"key", "message", "tag"}],   SpritzMac, 32 bytes
+//     "aead": [{"key", "nonce", "ad", "plaintext", "sealed"}]
+//         SpritzAead::seal, so sealed is ciphertext || 32 byte tag
+//   }
+//
+// the inputs are fixed, so the file only changes if the crate's output does.
+fn kat(argv: &[String]) -> CmdResult {
+	let a = Args::parse(argv, &["--out"], &[])?;
+	a.operand()?.map_or(Ok(()), |x| Err(format!("unexpected argument {}", x)))?;
+	let seq = |n: usize| (0 .. n).map(|i| i as u8).collect::<Vec<u8>>();
+	let key32 = seq(32);
+	let messages: [&[u8]; 5] = [b"", b"ABC", b"spam", b"arcfour", &seq(200)];
+	let key = Key::try_from(&key32[..]).unwrap();
+
+	let mut keystream = vec![];
+	for (k, iv) in [(&b"ABC"[..], None), (b"spam", None), (b"arcfour", None), (&key32, None), (&key32, Some(seq(16)))] {
+		let mut e = match iv {
+			Some(ref iv) => Spritz::with_raw_key(k).iv(iv),
+			None => Spritz::with_raw_key(k).deterministic(),
+		};
+		let mut out = [0; 32];
+		e.apply_keystream(&mut out);
+		let iv = iv.map_or("null".to_string(), |v| format!("\"{}\"", to_hex(&v)));
+		keystream.push(format!("{{\"key\": \"{}\", \"iv\": {}, \"output\": \"{}\"}}", to_hex(k), iv, to_hex(&out)));
+	}
+	let mut hash = vec![];
+	let mut mac = vec![];
+	for m in &messages {
+		hash.push(format!("{{\"message\": \"{}\", \"digest\": \"{}\"}}", to_hex(m), to_hex(&Spritz::hash256(m))));
+		let mut t = SpritzMac::new(&key32);
+		t.update(m);
+		let tag = to_hex(t.finalize().as_bytes());
+		mac.push(format!("{{\"key\": \"{}\", \"message\": \"{}\", \"tag\": \"{}\"}}", to_hex(&key32), to_hex(m), tag));
+	}
+	let mut aead = vec![];
+	let cipher = SpritzAead::new(&key);
+	for (i, m) in messages.iter().enumerate() {
+		let nonce = Nonce::from_counter(i as u64);
+		let ad = &seq(i * 3)[..];
+		let sealed = to_hex(&cipher.seal(&nonce, ad, m));
+		aead.push(format!(
+			"{{\"key\": \"{}\", \"nonce\": \"{}\", \"ad\": \"{}\", \"plaintext\": \"{}\", \"sealed\": \"{}\"}}",
+			to_hex(&key32),
+			to_hex(nonce.as_bytes()),
+			to_hex(ad),
+			to_hex(m),
+			sealed
+		));
+	}
+
+	let section = |name: &str, v: &[String]| format!("  \"{}\": [\n    {}\n  ]", name, v.join(",\n    "));
+	let json = format!(
+		"{{\n  \"schema\": \"spritz-kat/1\",\n{},\n{},\n{},\n{}\n}}\n",
+		section("keystream", &keystream),
+		section("hash", &hash),
+		section("mac", &mac),
+		section("aead", &aead)
+	);
+	let mut out = Output::create(a.get("--out"))?;
+	out.w.write_all(json.as_bytes()).map_err(|e| fail(out.name(), e))?;
+	out.finish()
+}
+
+// an archive is a nonce and then one stream in the chunked format, so it is
+// authenticated as a whole. the plaintext is a list of files
+//
+//   1 || name length (2, big endian) || name || size (8, big endian) || data
+//
+// ended by a single 0. names are relative and '/'-separated.
+const ARCHIVE_FILE: u8 = 1;
+const ARCHIVE_END: u8 = 0;
+
+fn archive(argv: &[String]) -> CmdResult {
+	let (sub, rest) = argv.split_first().ok_or("archive needs create, extract or list")?;
+	let a = Args::parse(rest, &with_key_opts(&["-C"]), &[])?;
+	match sub.as_str() {
+		"create" => archive_create(&a),
+		"extract" => archive_read(&a, true),
+		"list" => archive_read(&a, false),
+		s => Err(format!("unknown archive command {}", s)),
+	}
+}
+
+// leading '/' and "." parts are dropped and ".." is refused; with
+// extract_path refusing symbolic links, extracting can't write outside the
+// target directory.
+fn member_name(p: &str) -> Result<String, String> {
+	let parts: Vec<&str> = p.split('/').filter(|c| !c.is_empty() && *c != ".").collect();
+	if parts.is_empty() || parts.contains(&"..") {
+		return Err(format!("{}: unsafe path for an archive", p));
+	}
+	Ok(parts.join("/"))
+}
+
+// dir joined with a member name, making the directories on the way. each
+// part has to be missing or a real directory (the last a file): a symbolic
+// link already in the target is refused rather than followed.
+fn extract_path(dir: &Path, name: &str) -> Result<PathBuf, String> {
+	let mut p = dir.to_path_buf();
+	let parts: Vec<&str> = name.split('/').collect();
+	for (i, part) in parts.iter().enumerate() {
+		p.push(part);
+		let last = i == parts.len() - 1;
+		let shown = p.display().to_string();
+		match fs::symlink_metadata(&p) {
+			Ok(m) if m.file_type().is_symlink() => return Err(format!("{}: is a symbolic link", shown)),
+			Ok(m) if !last && !m.is_dir() => return Err(format!("{}: not a directory", shown)),
+			Ok(_) => {}
+			Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+				if !last {
+					fs::create_dir(&p).map_err(|e| fail(&shown, e))?;
+				}
+			}
+			Err(e) => return Err(fail(&shown, e)),
+		}
+	}
+	Ok(p)
+}
+
+fn archive_create(a: &Args) -> CmdResult {
+	let (archive, inputs) = a.free.split_first().ok_or("archive create needs an archive and files")?;
+	if inputs.is_empty() {
+		return Err("archive create needs files to add".to_string());
+	}
+	let mut files = vec![];
+	for p in inputs {
+		if Path::new(p).is_dir() {
+			walk(Path::new(p), &mut files)?;
+		} else {
+			files.push(p.clone());
+		}
+	}
+	let key = read_key(a)?;
+	let mut out = Output::create(Some(archive))?;
+	let nonce = Nonce::generate(&mut SpritzRng::from_os().map_err(|e| fail("/dev/urandom", e))?);
+	out.w.write_all(nonce.as_bytes()).map_err(|e| fail(archive, e))?;
+	let mut enc = EncryptWriter::new(&mut out.w, &key, &nonce);
+	for f in &files {
+		let name = member_name(f)?;
+		if name.len() > u16::MAX as usize {
+			return Err(format!("{}: name too long", f));
+		}
+		let file = File::open(f).map_err(|e| fail(f, e))?;
+		let size = file.metadata().map_err(|e| fail(f, e))?.len();
+		let mut header = vec![ARCHIVE_FILE];
+		header.extend_from_slice(&(name.len() as u16).to_be_bytes());
+		header.extend_from_slice(name.as_bytes());
+		header.extend_from_slice(&size.to_be_bytes());
+		enc.write_all(&header).map_err(|e| fail(archive, e))?;
+		if io::copy(&mut file.take(size), &mut enc).map_err(|e| fail(f, e))? != size {
+			return Err(format!("{}: changed while being read", f));
+		}
+	}
+	enc.write_all(&[ARCHIVE_END]).map_err(|e| fail(archive, e))?;
+	enc.finish().map_err(|e| fail(archive, e))?;
+	out.finish()
+}
+
+// list still reads every file, since that is what authenticates them. each
+// extracted file is written atomically, but one found bad partway through
+// an archive leaves the ones before it in place, as tar does.
+fn archive_read(a: &Args, extract: bool) -> CmdResult {
+	let archive = a.operand()?.ok_or("archive needs an archive")?;
+	let key = read_key(a)?;
+	let dir = Path::new(a.get("-C").unwrap_or("."));
+	let mut f = File::open(archive).map_err(|e| fail(archive, e))?;
+	let mut n = [0; 16];
+	f.read_exact(&mut n).map_err(|_| format!("{}: not a spritz archive", archive))?;
+	let mut r = DecryptReader::new(f, &key, &Nonce::new(n));
+	let bad = |e: io::Error| fail(archive, e);
+	loop {
+		let mut kind = [0];
+		r.read_exact(&mut kind).map_err(bad)?;
+		if kind[0] == ARCHIVE_END {
+			// reading to the end checks the final chunk.
+			if r.read(&mut [0]).map_err(bad)? != 0 {
+				return Err(format!("{}: data after the end of the archive", archive));
+			}
+			return Ok(());
+		}
+		if kind[0] != ARCHIVE_FILE {
+			return Err(format!("{}: not a spritz archive", archive));
+		}
+		let mut len = [0; 2];
+		r.read_exact(&mut len).map_err(bad)?;
+		let mut name = vec![0; u16::from_be_bytes(len) as usize];
+		r.read_exact(&mut name).map_err(bad)?;
+		let name = String::from_utf8(name).map_err(|_| format!("{}: bad file name", archive))?;
+		if member_name(&name)? != name {
+			return Err(format!("{}: unsafe path in archive", name));
+		}
+		let mut size = [0; 8];
+		r.read_exact(&mut size).map_err(bad)?;
+		let size = u64::from_be_bytes(size);
+		let mut data = (&mut r).take(size);
+		let copied = if extract {
+			let dest = extract_path(dir, &name)?;
+			let dest = dest.to_str().ok_or("bad output path")?.to_string();
+			let mut out = Output::create(Some(&dest))?;
+			let copied = io::copy(&mut data, &mut out.w).map_err(bad)?;
+			if copied == size {
+				out.finish()?;
+			}
+			copied
+		} else {
+			println!("{:>12}  {}", size, name);
+			io::copy(&mut data, &mut io::sink()).map_err(bad)?
+		};
+		if copied != size {
+			return Err(format!("{}: truncated archive", archive));
+		}
+	}
+}
+
+#[cfg(test)]
+fn strings(v: &[&str]) -> Vec<String> {
+	v.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn args() {
+	let a = Args::parse(&strings(&["--key-env=K", "-o", "out", "-", "--x"][.. 4]), &["--key-env", "-o"], &["--x"]).unwrap();
+	assert_eq!(a.get("--key-env"), Some("K"));
+	assert_eq!(a.get("-o"), Some("out"));
+	assert_eq!(a.operand(), Ok(None));
+	let a = Args::parse(&strings(&["--x", "--", "--key-env"]), &["--key-env"], &["--x"]).unwrap();
+	assert_eq!(a.switches, ["--x"]);
+	assert_eq!(a.operand(), Ok(Some("--key-env")));
+	assert!(Args::parse(&strings(&["--y"]), &[], &[]).is_err());
+	assert!(Args::parse(&strings(&["-o"]), &["-o"], &[]).is_err());
+
+	assert!(parse_key(&[1; 32]).is_ok());
+	assert_eq!(parse_key(format!("{}\n", "ab".repeat(32)).as_bytes()).unwrap().as_bytes(), &[0xab; 32][..]);
+	assert!(parse_key(b"abcd").is_err());
+}
+
+#[test]
+fn passwords() {
+	let charset: Vec<char> = "ab€".chars().collect();
+	let p = password(&mut SpritzRng::new(b"seed"), &charset, 50);
+	assert_eq!(p.chars().count(), 50);
+	assert!(p.chars().all(|c| charset.contains(&c)));
+	assert_eq!(p, password(&mut SpritzRng::new(b"seed"), &charset, 50));
+}
+
+#[test]
+fn iterations() {
+	let mut h = [0; KDF_HEADER_LEN];
+	for (n, ok) in [(MIN_ITERATIONS, true), (MAX_ITERATIONS, true), (MIN_ITERATIONS - 1, false), (MAX_ITERATIONS + 1, false), (u32::MAX, false)] {
+		h[SALT_LEN ..].copy_from_slice(&n.to_be_bytes());
+		assert_eq!(stored_iterations("f", &h).is_ok(), ok);
+	}
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks() {
+	use std::os::unix::fs::symlink;
+
+	let root = env::temp_dir().join(format!("spritz-symlinks-test-{}", process::id()));
+	let (dir, outside) = (root.join("dir"), root.join("outside"));
+	fs::create_dir_all(&dir).unwrap();
+	fs::create_dir_all(&outside).unwrap();
+	symlink(&outside, dir.join("link")).unwrap();
+	symlink(&dir, dir.join("loop")).unwrap();
+	fs::write(dir.join("file"), b"x").unwrap();
+
+	assert!(extract_path(&dir, "link/x").is_err());
+	assert!(extract_path(&dir, "link").is_err());
+	assert!(extract_path(&dir, "file/x").is_err());
+	assert_eq!(extract_path(&dir, "a/b/c").unwrap(), dir.join("a/b/c"));
+	assert!(dir.join("a/b").is_dir() && !dir.join("a/b/c").exists());
+
+	let mut files = vec![];
+	walk(&dir, &mut files).unwrap();
+	assert_eq!(files, [dir.join("file").display().to_string()]);
+	fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn member_names() {
+	assert_eq!(member_name("/a//./b/c").unwrap(), "a/b/c");
+	assert!(member_name("a/../../b").is_err());
+	assert!(member_name("/").is_err());
+}
@@ -0,0 +1,176 @@
+// spritz with the permutation stored blinded: every entry of S is kept
+// xored with a random mask byte, and a new mask is put in after every
+// shuffle. a dump of the memory, or a probe of one entry, then doesn't show
+// the permutation directly.
+//
+// it's a light countermeasure, not a proof of anything: the mask is one
+// byte, and the registers are kept as they are. swaps work on the blinded
+// values as they are, so only reads that feed an index, a comparison or
+// the output pay for unmasking. the output is exactly Spritz's.
+
+use rng::SpritzRng;
+use N;
+
+#[derive(Clone)]
+pub struct BlindedSpritz {
+	S: [u8; N],
+	m: u8,
+	i: u8,
+	j: u8,
+	k: u8,
+	z: u8,
+	a: u8,
+	w: u8,
+	// xorshift64 state for the mask refreshes, seeded from rng in new().
+	x: u64,
+}
+
+impl BlindedSpritz {
+	pub fn new(key: &[u8], rng: &mut SpritzRng) -> BlindedSpritz {
+		let m = rng.next_u32() as u8;
+		let mut S = [0; N];
+		for (i, v) in S.iter_mut().enumerate() {
+			*v = i as u8 ^ m;
+		}
+		let mut sp = BlindedSpritz { S, m, i: 0, j: 0, k: 0, z: 0, a: 0, w: 1, x: rng.next_u64() | 1 };
+		sp.absorb(key);
+		sp
+	}
+
+	fn get(&self, x: u8) -> u8 {
+		self.S[x as usize] ^ self.m
+	}
+
+	// replace the mask with a fresh one, without unmasking S on the way.
+	pub fn remask(&mut self) {
+		self.x ^= self.x << 13;
+		self.x ^= self.x >> 7;
+		self.x ^= self.x << 17;
+		let d = self.m ^ self.x as u8;
+		for v in self.S.iter_mut() {
+			*v ^= d;
+		}
+		self.m ^= d;
+	}
+
+	pub fn absorb(&mut self, data: &[u8]) {
+		for b in data {
+			self.absorb_nibble(b & 0xf);
+			self.absorb_nibble(b >> 4);
+		}
+	}
+
+	fn absorb_nibble(&mut self, x: u8) {
+		if self.a as usize == N / 2 {
+			self.shuffle();
+		}
+		self.S.swap(self.a as usize, N / 2 + x as usize);
+		self.a = self.a.wrapping_add(1);
+	}
+
+	pub fn absorb_stop(&mut self) {
+		if self.a as usize == N / 2 {
+			self.shuffle();
+		}
+		self.a = self.a.wrapping_add(1);
+	}
+
+	fn shuffle(&mut self) {
+		self.whip(2 * N);
+		self.crush();
+		self.whip(2 * N);
+		self.crush();
+		self.whip(2 * N);
+		self.a = 0;
+		self.remask();
+	}
+
+	fn whip(&mut self, r: usize) {
+		for _ in 0 .. r {
+			self.update();
+		}
+		self.w = self.w.wrapping_add(2);
+	}
+
+	// branchless, as in Spritz::crush; xoring both sides by the same mask
+	// keeps them blinded.
+	fn crush(&mut self) {
+		for v in 0 .. N / 2 {
+			let (x, y) = (self.get(v as u8), self.get((N - 1 - v) as u8));
+			let m = ((y as u16).wrapping_sub(x as u16) >> 8) as u8;
+			let d = (x ^ y) & m;
+			self.S[v] ^= d;
+			self.S[N - 1 - v] ^= d;
+		}
+	}
+
+	fn update(&mut self) {
+		self.i = self.i.wrapping_add(self.w);
+		let idx = self.j.wrapping_add(self.get(self.i));
+		self.j = self.k.wrapping_add(self.get(idx));
+		self.k = self.i.wrapping_add(self.k).wrapping_add(self.get(self.j));
+		self.S.swap(self.i as usize, self.j as usize);
+	}
+
+	fn output(&mut self) -> u8 {
+		let t0 = self.get(self.z.wrapping_add(self.k));
+		let t1 = self.get(self.i.wrapping_add(t0));
+		self.z = self.get(self.j.wrapping_add(t1));
+		self.z
+	}
+
+	pub fn drip(&mut self) -> u8 {
+		if self.a > 0 {
+			self.shuffle();
+		}
+		self.update();
+		self.output()
+	}
+
+	pub fn squeeze_into(&mut self, out: &mut [u8]) {
+		for v in out.iter_mut() {
+			*v = self.drip();
+		}
+	}
+
+	pub fn apply_keystream(&mut self, buf: &mut [u8]) {
+		for v in buf.iter_mut() {
+			*v ^= self.drip();
+		}
+	}
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for BlindedSpritz {
+	fn drop(&mut self) {
+		::zeroize(&mut self.S);
+		let regs = [&mut self.m, &mut self.i, &mut self.j, &mut self.k, &mut self.z, &mut self.a, &mut self.w];
+		for r in regs {
+			::zeroize(::core::slice::from_mut(r));
+		}
+	}
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn blinded() {
+	use Spritz;
+
+	let mut rng = SpritzRng::new(b"masks");
+	let mut a = BlindedSpritz::new(b"key", &mut rng);
+	let mut b = Spritz::new(b"key");
+	let m = a.m;
+	a.absorb_stop();
+	b.absorb_stop();
+	a.absorb(&[0x5a; 200]);
+	b.absorb(&[0x5a; 200]);
+	let mut x = [0; 300];
+	a.squeeze_into(&mut x);
+	assert_eq!(x[..], b.squeeze(300)[..]);
+	assert!(a.m != m);
+	a.remask();
+	let mut y = [0; 300];
+	a.apply_keystream(&mut y);
+	assert_eq!(y[..], b.squeeze(300)[..]);
+	assert!(a.S[..] != b.S[..]);
+}
@@ -0,0 +1,110 @@
+// bidirectional authenticated session over a shared key.
+//
+// each direction has its own protocol state. a frame is the 8-byte big
+// endian sequence number, the ciphertext and a 16-byte tag. the sequence
+// number is also bound into the state, so dropped, replayed or reordered
+// frames fail to open.
+
+use alloc::vec::Vec;
+
+use error::{Error, Result};
+use protocol::Protocol;
+
+const SEQ_LEN: usize = 8;
+pub const TAG_LEN: usize = 16;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Role {
+	Initiator,
+	Responder,
+}
+
+pub struct SecureChannel {
+	tx: Protocol,
+	rx: Protocol,
+	tx_seq: u64,
+	rx_seq: u64,
+}
+
+fn direction(key: &[u8], session_id: &[u8], label: &[u8]) -> Protocol {
+	let mut p = Protocol::new(b"spritz secure channel");
+	p.key(key);
+	p.ad(session_id);
+	p.ad(label);
+	p
+}
+
+impl SecureChannel {
+	pub fn new(key: &[u8], session_id: &[u8], role: Role) -> SecureChannel {
+		let i2r = direction(key, session_id, b"initiator to responder");
+		let r2i = direction(key, session_id, b"responder to initiator");
+		let (tx, rx) = match role {
+			Role::Initiator => (i2r, r2i),
+			Role::Responder => (r2i, i2r),
+		};
+		SecureChannel { tx, rx, tx_seq: 0, rx_seq: 0 }
+	}
+
+	// fails once the sequence number would wrap, after 2^64 - 1 frames,
+	// rather than reuse one.
+	pub fn send(&mut self, plaintext: &[u8]) -> Result<Vec<u8>> {
+		let seq = self.tx_seq.to_be_bytes();
+		self.tx_seq = self.tx_seq.checked_add(1).ok_or(Error::Exhausted)?;
+		self.tx.ad(&seq);
+
+		let mut frame = Vec::with_capacity(SEQ_LEN + plaintext.len() + TAG_LEN);
+		frame.extend_from_slice(&seq);
+		frame.extend_from_slice(plaintext);
+		self.tx.send_enc(&mut frame[SEQ_LEN ..]);
+		let mut tag = [0; TAG_LEN];
+		self.tx.send_mac(&mut tag);
+		frame.extend_from_slice(&tag);
+		Ok(frame)
+	}
+
+	// on error the receive state is left as it was.
+	pub fn recv(&mut self, frame: &[u8]) -> Result<Vec<u8>> {
+		if frame.len() < SEQ_LEN + TAG_LEN {
+			return Err(Error::InvalidLength);
+		}
+		let (seq, rest) = frame.split_at(SEQ_LEN);
+		let (ct, tag) = rest.split_at(rest.len() - TAG_LEN);
+		if seq != self.rx_seq.to_be_bytes() {
+			return Err(Error::UnexpectedSequence);
+		}
+
+		let mut rx = self.rx.clone();
+		rx.ad(seq);
+		let mut pt = ct.to_vec();
+		rx.recv_enc(&mut pt);
+		rx.recv_mac(tag)?;
+		self.rx = rx;
+		self.rx_seq += 1;
+		Ok(pt)
+	}
+}
+
+#[test]
+fn channel() {
+	let mut a = SecureChannel::new(b"key", b"session", Role::Initiator);
+	let mut b = SecureChannel::new(b"key", b"session", Role::Responder);
+
+	let f1 = a.send(b"one").unwrap();
+	let f2 = a.send(b"two").unwrap();
+	assert_eq!(f1.len(), SEQ_LEN + 3 + TAG_LEN);
+	assert_eq!(b.recv(&f2), Err(Error::UnexpectedSequence));
+	assert_eq!(b.recv(&f1).unwrap(), b"one");
+	assert_eq!(b.recv(&f1), Err(Error::UnexpectedSequence));
+
+	let mut bad = f2.clone();
+	bad[SEQ_LEN] ^= 1;
+	assert_eq!(b.recv(&bad), Err(Error::InvalidTag));
+	assert_eq!(b.recv(&f2).unwrap(), b"two");
+
+	let r = b.send(b"reply").unwrap();
+	assert_eq!(a.recv(&r).unwrap(), b"reply");
+	assert_eq!(a.recv(&r[.. 4]), Err(Error::InvalidLength));
+
+	a.tx_seq = u64::MAX;
+	assert_eq!(a.send(b"last"), Err(Error::Exhausted));
+}
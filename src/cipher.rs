@@ -0,0 +1,191 @@
+// the stream cipher with the nonce made part of the type: keystream only
+// comes from an Encryptor, and the only ways to get one are to give a
+// nonce or to say outright that the key is used for one message.
+//
+//   let mut e = Spritz::with_key(&key).nonce(&nonce);
+//   e.apply_keystream(&mut buf);
+//
+// Spritz itself has no apply_keystream outside the crate, and the io
+// adapters, the pipeline and the FFI stream all take an Encryptor. a bare
+// Spritz from new or new_with_iv is the sponge, for building other
+// constructions on. its raw output, squeeze and drip, and the deprecated
+// Spritz::xor_key_stream are the ways round all this, and ask for no nonce.
+//
+// KeystreamExt does the same for iterators of bytes:
+//
+//   let ct: Vec<u8> = bytes.spritz_encrypt(&key, &nonce).collect();
+
+use error::{Error, Result};
+use key::{Key, Nonce};
+use metrics::Metrics;
+use {wipe_temp, Spritz, KEYSTREAM_BLOCK};
+
+// a key with nothing yet absorbed on top.
+pub struct Keyed {
+	sp: Spritz,
+}
+
+impl Keyed {
+	// the same keystream as Spritz::new_with_iv(key, nonce).
+	pub fn nonce(self, nonce: &Nonce) -> Encryptor {
+		self.iv(nonce.as_bytes())
+	}
+
+	// an iv of any length, as in section 4 of the paper, for keystreams
+	// that have to match other implementations.
+	pub fn iv(mut self, iv: &[u8]) -> Encryptor {
+		self.sp.absorb_stop();
+		self.sp.absorb(iv);
+		Encryptor { sp: self.sp }
+	}
+
+	// no nonce, the same keystream as Spritz::new(key). only safe when the
+	// key encrypts a single message, e.g. one derived for it.
+	pub fn deterministic(self) -> Encryptor {
+		Encryptor { sp: self.sp }
+	}
+}
+
+pub struct Encryptor {
+	pub(crate) sp: Spritz,
+}
+
+impl Encryptor {
+	// encrypt or decrypt buf in place, continuing the keystream.
+	pub fn apply_keystream(&mut self, buf: &mut [u8]) {
+		self.sp.apply_keystream(buf);
+	}
+
+	pub fn apply_keystream_vectored(&mut self, bufs: &mut [&mut [u8]]) {
+		self.sp.apply_keystream_vectored(bufs);
+	}
+
+	pub fn xor_key_stream(&mut self, dst: &mut [u8], src: &[u8]) -> Result<()> {
+		if dst.len() != src.len() {
+			return Err(Error::UnexpectedLength { expected: src.len(), actual: dst.len() });
+		}
+		dst.copy_from_slice(src);
+		self.sp.apply_keystream(dst);
+		Ok(())
+	}
+
+	// bytes is the keystream used so far.
+	pub fn metrics(&self) -> Metrics {
+		let u = self.sp.usage();
+		Metrics { bytes: u.squeezed, shuffles: u.shuffles, ..Metrics::default() }
+	}
+}
+
+impl Spritz {
+	/// Keystream needs a nonce, or an explicit `deterministic()`:
+	///
+	/// ```compile_fail,E0599
+	/// # use spritz::{Key, Spritz};
+	/// let mut buf = [0; 16];
+	/// Spritz::with_key(&Key::new([1; 32])).apply_keystream(&mut buf);
+	/// ```
+	///
+	/// and a bare state has no keystream method to fall back on:
+	///
+	/// ```compile_fail,E0624
+	/// # use spritz::Spritz;
+	/// let mut buf = [0; 16];
+	/// Spritz::new(b"key").apply_keystream(&mut buf);
+	/// ```
+	pub fn with_key(key: &Key) -> Keyed {
+		Spritz::with_raw_key(key.as_bytes())
+	}
+
+	// a key of any length, as the paper's test vectors use.
+	pub fn with_raw_key(key: &[u8]) -> Keyed {
+		Keyed { sp: Spritz::new(key) }
+	}
+}
+
+// xors the keystream into the bytes of an iterator as they're pulled,
+// a block of keystream at a time.
+pub struct Keystream<I> {
+	it: I,
+	e: Encryptor,
+	ks: [u8; KEYSTREAM_BLOCK],
+	pos: usize,
+}
+
+impl<I: Iterator<Item = u8>> Iterator for Keystream<I> {
+	type Item = u8;
+
+	fn next(&mut self) -> Option<u8> {
+		let b = self.it.next()?;
+		if self.pos == KEYSTREAM_BLOCK {
+			self.ks = [0; KEYSTREAM_BLOCK];
+			self.e.apply_keystream(&mut self.ks);
+			self.pos = 0;
+		}
+		self.pos += 1;
+		Some(b ^ self.ks[self.pos - 1])
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.it.size_hint()
+	}
+}
+
+impl<I> Drop for Keystream<I> {
+	fn drop(&mut self) {
+		wipe_temp(&mut self.ks);
+	}
+}
+
+pub trait KeystreamExt: Iterator<Item = u8> + Sized {
+	// the same bytes as Spritz::with_key(key).nonce(nonce) applied to the
+	// whole sequence.
+	fn spritz_encrypt(self, key: &Key, nonce: &Nonce) -> Keystream<Self> {
+		Keystream { it: self, e: Spritz::with_key(key).nonce(nonce), ks: [0; KEYSTREAM_BLOCK], pos: KEYSTREAM_BLOCK }
+	}
+
+	// the keystream is its own inverse; this is spritz_encrypt by name.
+	fn spritz_decrypt(self, key: &Key, nonce: &Nonce) -> Keystream<Self> {
+		self.spritz_encrypt(key, nonce)
+	}
+}
+
+impl<I: Iterator<Item = u8>> KeystreamExt for I {}
+
+#[test]
+fn cipher() {
+	let key = Key::new([3; 32]);
+	let n = Nonce::from_counter(9);
+	let mut a = [0; 100];
+	let mut b = [0; 100];
+	Spritz::with_key(&key).nonce(&n).apply_keystream(&mut a);
+	Spritz::new_with_iv(key.as_bytes(), n.as_bytes()).apply_keystream(&mut b);
+	assert_eq!(a[..], b[..]);
+
+	let mut c = [0; 100];
+	Spritz::with_key(&key).deterministic().apply_keystream(&mut c);
+	let mut d = [0; 100];
+	Spritz::new(key.as_bytes()).apply_keystream(&mut d);
+	assert_eq!(c[..], d[..]);
+	assert!(a[..] != c[..]);
+
+	let mut e = [0; 100];
+	Spritz::with_raw_key(b"arcfour").iv(b"iv").apply_keystream(&mut e);
+	let mut f = [0; 100];
+	Spritz::new_with_iv(b"arcfour", b"iv").apply_keystream(&mut f);
+	assert_eq!(e[..], f[..]);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn iterator() {
+	let key = Key::new([3; 32]);
+	let n = Nonce::from_counter(9);
+	let msg: Vec<u8> = (0 .. 200).map(|i| i as u8).collect();
+	let ct: Vec<u8> = msg.iter().copied().spritz_encrypt(&key, &n).collect();
+	let mut want = msg.clone();
+	Spritz::with_key(&key).nonce(&n).apply_keystream(&mut want);
+	assert_eq!(ct, want);
+	let pt: Vec<u8> = ct.into_iter().spritz_decrypt(&key, &n).collect();
+	assert_eq!(pt, msg);
+	assert_eq!(msg.iter().copied().spritz_encrypt(&key, &n).take(3).count(), 3);
+}
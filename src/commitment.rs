@@ -0,0 +1,48 @@
+// commit/reveal commitments.
+//
+// the commitment is a keyed hash of the value under a random opening key:
+// hiding because the key is secret until reveal, binding because finding a
+// second (value, opening) pair means finding a collision.
+
+use ct_eq;
+use protocol::Protocol;
+use rng::SpritzRng;
+
+pub const COMMITMENT_LEN: usize = 32;
+pub const OPENING_LEN: usize = 32;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Commitment(pub [u8; COMMITMENT_LEN]);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Opening(pub [u8; OPENING_LEN]);
+
+fn digest(value: &[u8], opening: &Opening) -> [u8; COMMITMENT_LEN] {
+	let mut p = Protocol::new(b"spritz commitment");
+	p.key(&opening.0);
+	p.ad(value);
+	let mut c = [0; COMMITMENT_LEN];
+	p.prf(&mut c);
+	c
+}
+
+pub fn commit(value: &[u8], rng: &mut SpritzRng) -> (Commitment, Opening) {
+	let mut o = Opening([0; OPENING_LEN]);
+	rng.fill_bytes(&mut o.0);
+	(Commitment(digest(value, &o)), o)
+}
+
+pub fn verify(commitment: &Commitment, value: &[u8], opening: &Opening) -> bool {
+	ct_eq(&digest(value, opening), &commitment.0)
+}
+
+#[test]
+fn commitment() {
+	let mut rng = SpritzRng::new(b"test");
+	let (c, o) = commit(b"bid: 100", &mut rng);
+	assert!(verify(&c, b"bid: 100", &o));
+	assert!(!verify(&c, b"bid: 101", &o));
+	let (c2, o2) = commit(b"bid: 100", &mut rng);
+	assert!(c != c2);
+	assert!(!verify(&c, b"bid: 100", &o2));
+}
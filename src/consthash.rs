@@ -0,0 +1,99 @@
+// the hash as const fns, for digests worked out at compile time:
+//
+//   const ID: [u8; 32] = spritz::const_hash256(b"spritz");
+//
+// the same digests as Spritz::hash_array. absorb, squeeze and shuffle are
+// written again here nibble by nibble, since the runtime ones call hooks
+// and keep usage; update, output, crush and the nibble swap are the
+// runtime ones. the state is forgotten rather than dropped, so called at
+// run time nothing is wiped even with the zeroize feature.
+
+use core::mem;
+
+use {Spritz, HALF, N};
+
+impl Spritz {
+	const fn const_whip(&mut self) {
+		let mut r = 0;
+		while r < 2 * N {
+			self.update();
+			r += 1;
+		}
+		self.w = self.w.wrapping_add(2);
+	}
+
+	const fn const_shuffle(&mut self) {
+		self.const_whip();
+		self.crush_pairs();
+		self.const_whip();
+		self.crush_pairs();
+		self.const_whip();
+		self.a = 0;
+	}
+
+	const fn const_absorb_nibble(&mut self, x: u8) {
+		if self.a == HALF {
+			self.const_shuffle();
+		}
+		self.swap_nibble(self.a, x);
+		self.a = self.a.wrapping_add(1);
+	}
+
+	const fn const_absorb(&mut self, I: &[u8]) {
+		let mut v = 0;
+		while v < I.len() {
+			self.const_absorb_nibble(I[v] & 0xf);
+			self.const_absorb_nibble(I[v] >> 4);
+			v += 1;
+		}
+	}
+
+	const fn const_absorb_stop(&mut self) {
+		if self.a == HALF {
+			self.const_shuffle();
+		}
+		self.a = self.a.wrapping_add(1);
+	}
+
+	const fn const_squeeze(&mut self, out: &mut [u8]) {
+		if self.a > 0 {
+			self.const_shuffle();
+		}
+		let mut v = 0;
+		while v < out.len() {
+			self.update();
+			out[v] = self.output();
+			v += 1;
+		}
+	}
+}
+
+// R over 255 fails to build, as for Spritz::hash_array.
+pub const fn const_hash_array<const R: usize>(msg: &[u8]) -> [u8; R] {
+	const { assert!(R <= u8::MAX as usize, "spritz: hash output over 255 bytes") };
+	let mut sp = Spritz::initialize_state();
+	sp.const_absorb(msg);
+	sp.const_absorb_stop();
+	sp.const_absorb(&[R as u8]);
+	let mut out = [0; R];
+	sp.const_squeeze(&mut out);
+	// Spritz only needs dropping with the zeroize or hooks features, and a
+	// const fn can't run a destructor.
+	#[allow(clippy::forget_non_drop)]
+	mem::forget(sp);
+	out
+}
+
+pub const fn const_hash256(msg: &[u8]) -> [u8; 32] {
+	const_hash_array(msg)
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn const_hash() {
+	const ABC: [u8; 32] = const_hash256(b"ABC");
+	assert_eq!(ABC[..], Spritz::hash256(b"ABC")[..]);
+	let long: Vec<u8> = (0 .. 300).map(|i| i as u8).collect();
+	assert_eq!(const_hash_array::<7>(&long), Spritz::hash_array::<7>(&long));
+	assert_eq!(const_hash_array::<0>(b""), [0; 0]);
+}
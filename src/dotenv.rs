@@ -0,0 +1,203 @@
+// encrypted .env files.
+//
+//   magic (8) || nonce (16) || sealed text
+//
+// the text is an ordinary dotenv file, sealed whole with the magic as
+// associated data: NAME=value lines, with blank lines, # comments and an
+// "export " prefix allowed. values may be in single quotes, taken as they
+// are, or double quotes, where \n, \", and \\ are escapes. the key comes
+// from a KeySource, the same forms the CLI takes keys in.
+
+use core::convert::TryFrom;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use aead::SpritzAead;
+use error::{Error, Result};
+#[cfg(feature = "kdf")]
+use keystore::Keystore;
+use key::{Key, Nonce, KEY_LEN, NONCE_LEN};
+use rng::SpritzRng;
+use zeroize;
+
+const MAGIC: &[u8; 8] = b"SPZENV\x00\x01";
+
+pub enum KeySource<'a> {
+	// 64 hex digits in the named environment variable.
+	Env(&'a str),
+	// a file of 32 raw bytes or 64 hex digits.
+	File(&'a Path),
+	// the key under name in a Keystore file.
+	#[cfg(feature = "kdf")]
+	Keystore { path: &'a Path, passphrase: &'a [u8], name: &'a str },
+}
+
+impl<'a> KeySource<'a> {
+	pub fn key(&self) -> io::Result<Key> {
+		match *self {
+			KeySource::Env(var) => {
+				let v = env::var(var).map_err(|e| io::Error::new(io::ErrorKind::NotFound, e))?;
+				Ok(parse_key(v.as_bytes())?)
+			}
+			KeySource::File(path) => {
+				let mut data = fs::read(path)?;
+				let k = parse_key(&data);
+				zeroize(&mut data);
+				Ok(k?)
+			}
+			#[cfg(feature = "kdf")]
+			KeySource::Keystore { path, passphrase, name } => Keystore::load(path, passphrase)?
+				.remove(name)
+				.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "spritz: no such key in the keystore")),
+		}
+	}
+}
+
+fn parse_key(data: &[u8]) -> Result<Key> {
+	if data.len() == KEY_LEN {
+		return Key::try_from(data);
+	}
+	let hex = core::str::from_utf8(data).map_err(|_| Error::InvalidEncoding)?.trim();
+	if hex.len() != 2 * KEY_LEN || !hex.bytes().all(|c| c.is_ascii_hexdigit()) {
+		return Err(Error::InvalidEncoding);
+	}
+	let mut k = [0; KEY_LEN];
+	for (v, i) in k.iter_mut().zip((0 .. hex.len()).step_by(2)) {
+		*v = u8::from_str_radix(&hex[i .. i + 2], 16).map_err(|_| Error::InvalidEncoding)?;
+	}
+	Ok(Key::new(k))
+}
+
+// the variables of a decrypted file, in file order.
+#[derive(Clone, Debug, Default)]
+pub struct EnvFile {
+	vars: Vec<(String, String)>,
+}
+
+impl EnvFile {
+	pub fn load<P: AsRef<Path>>(path: P, key: &KeySource) -> io::Result<EnvFile> {
+		let sealed = fs::read(path)?;
+		Ok(EnvFile::open(&sealed, &key.key()?)?)
+	}
+
+	pub fn open(sealed: &[u8], key: &Key) -> Result<EnvFile> {
+		if sealed.len() < MAGIC.len() + NONCE_LEN {
+			return Err(Error::Truncated);
+		}
+		let (head, body) = sealed.split_at(MAGIC.len() + NONCE_LEN);
+		if &head[.. MAGIC.len()] != MAGIC {
+			return Err(Error::InvalidLength);
+		}
+		let nonce = Nonce::try_from(&head[MAGIC.len() ..])?;
+		let mut text = SpritzAead::new(key).open(&nonce, MAGIC, body)?;
+		let r = core::str::from_utf8(&text).map_err(|_| Error::InvalidEncoding).and_then(EnvFile::parse);
+		zeroize(&mut text);
+		r
+	}
+
+	fn parse(text: &str) -> Result<EnvFile> {
+		let mut vars = vec![];
+		for line in text.lines() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+			let line = line.strip_prefix("export ").unwrap_or(line);
+			let (name, value) = line.split_once('=').ok_or(Error::InvalidEncoding)?;
+			let name = name.trim();
+			if name.is_empty() || !name.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'_') {
+				return Err(Error::InvalidEncoding);
+			}
+			vars.push((name.to_string(), unquote(value.trim())?));
+		}
+		Ok(EnvFile { vars })
+	}
+
+	pub fn get(&self, name: &str) -> Option<&str> {
+		self.vars.iter().rev().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+		self.vars.iter().map(|(n, v)| (n.as_str(), v.as_str()))
+	}
+
+	// set each variable in this process's environment, leaving any that
+	// are already set alone, as dotenv loaders do. like env::set_var, only
+	// safe to call before other threads are started.
+	pub fn apply(&self) {
+		for (n, v) in self.iter() {
+			if env::var_os(n).is_none() {
+				env::set_var(n, v);
+			}
+		}
+	}
+}
+
+fn unquote(v: &str) -> Result<String> {
+	if let Some(inner) = v.strip_prefix('\'') {
+		return inner.strip_suffix('\'').map(str::to_string).ok_or(Error::InvalidEncoding);
+	}
+	let Some(inner) = v.strip_prefix('"') else {
+		return Ok(v.to_string());
+	};
+	let inner = inner.strip_suffix('"').ok_or(Error::InvalidEncoding)?;
+	let mut out = String::with_capacity(inner.len());
+	let mut chars = inner.chars();
+	while let Some(c) = chars.next() {
+		if c != '\\' {
+			out.push(c);
+			continue;
+		}
+		match chars.next() {
+			Some('n') => out.push('\n'),
+			Some(c @ ('"' | '\\')) => out.push(c),
+			_ => return Err(Error::InvalidEncoding),
+		}
+	}
+	Ok(out)
+}
+
+// the file EnvFile::open reads, from the dotenv text.
+pub fn seal_env(text: &str, key: &Key, rng: &mut SpritzRng) -> Vec<u8> {
+	let nonce = Nonce::generate(rng);
+	let mut out = MAGIC.to_vec();
+	out.extend_from_slice(nonce.as_bytes());
+	out.extend_from_slice(&SpritzAead::new(key).seal(&nonce, MAGIC, text.as_bytes()));
+	out
+}
+
+#[test]
+fn dotenv() {
+	let key = Key::new([4; KEY_LEN]);
+	let text = "# settings\n\nDB_URL=postgres://db/app\nexport TOKEN = 'a b#c'\nGREETING=\"hi\\n\\\"you\\\"\"\nTOKEN=second\n";
+	let sealed = seal_env(text, &key, &mut SpritzRng::new(b"test"));
+	let e = EnvFile::open(&sealed, &key).unwrap();
+	assert_eq!(e.iter().map(|(n, _)| n).collect::<Vec<_>>(), ["DB_URL", "TOKEN", "GREETING", "TOKEN"]);
+	assert_eq!(e.get("DB_URL"), Some("postgres://db/app"));
+	assert_eq!(e.get("GREETING"), Some("hi\n\"you\""));
+	assert_eq!(e.get("TOKEN"), Some("second"));
+	assert_eq!(e.get("MISSING"), None);
+	assert_eq!(EnvFile::open(&sealed, &Key::new([5; KEY_LEN])).err(), Some(Error::InvalidTag));
+	assert_eq!(EnvFile::open(&sealed[.. 10], &key).err(), Some(Error::Truncated));
+	let bad = seal_env("NO EQUALS SIGN\n", &key, &mut SpritzRng::new(b"test"));
+	assert_eq!(EnvFile::open(&bad, &key).err(), Some(Error::InvalidEncoding));
+
+	let var = format!("SPRITZ_DOTENV_TEST_KEY_{}", ::std::process::id());
+	env::set_var(&var, "04".repeat(KEY_LEN));
+	let path = env::temp_dir().join(format!("spritz-dotenv-test-{}", ::std::process::id()));
+	fs::write(&path, &sealed).unwrap();
+	let e = EnvFile::load(&path, &KeySource::Env(&var)).unwrap();
+	assert_eq!(e.get("DB_URL"), Some("postgres://db/app"));
+	fs::write(&path, [4; KEY_LEN]).unwrap();
+	assert_eq!(KeySource::File(&path).key().unwrap().as_bytes(), key.as_bytes());
+	fs::remove_file(&path).unwrap();
+
+	let set = format!("{}_SET", var);
+	EnvFile::parse(&format!("{}=new\n{}=x", set, var)).unwrap().apply();
+	assert_eq!(env::var(&set).unwrap(), "new");
+	assert_eq!(env::var(&var).unwrap(), "04".repeat(KEY_LEN));
+	env::remove_var(&var);
+	env::remove_var(&set);
+}
@@ -0,0 +1,208 @@
+// keyed duplex built on the spritz sponge.
+//
+// seal/open follow the AEAD in section 4 of the paper: the message is
+// processed in N/4 byte blocks, each one encrypted with squeezed output and
+// the ciphertext absorbed back so the state (and any tag) depends on it.
+
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+
+use sponge::SpongePermutation;
+use {ct_eq, wipe_temp, Phase, Spritz, Usage};
+
+pub(crate) const BLOCK: usize = 64;
+#[cfg(feature = "std")]
+const READ_CHUNK: usize = 4096;
+
+#[derive(Clone, Debug)]
+pub struct SpritzDuplex<P = Spritz> {
+	sp: P,
+}
+
+impl SpritzDuplex {
+	pub fn new(key: &[u8]) -> SpritzDuplex {
+		SpritzDuplex::with_permutation(key)
+	}
+
+	pub fn phase(&self) -> Phase {
+		self.sp.phase()
+	}
+
+	// see Spritz::ratchet.
+	pub fn ratchet(&mut self) {
+		self.sp.ratchet();
+		self.absorb_stop();
+	}
+}
+
+impl<P: SpongePermutation> SpritzDuplex<P> {
+	pub fn with_permutation(key: &[u8]) -> SpritzDuplex<P> {
+		let mut sp = P::initialize();
+		sp.absorb(key);
+		sp.absorb_stop();
+		SpritzDuplex { sp }
+	}
+
+	pub fn usage(&self) -> Usage {
+		self.sp.usage()
+	}
+
+	pub fn absorb(&mut self, data: &[u8]) {
+		self.sp.absorb(data);
+	}
+
+	// lengths are absorbed as 8 byte big endian, so ("ab", "c") and
+	// ("a", "bc") give different states when framed.
+	pub fn absorb_u64(&mut self, v: u64) {
+		self.absorb(&v.to_be_bytes());
+	}
+
+	pub fn absorb_len_prefixed(&mut self, data: &[u8]) {
+		self.absorb_u64(data.len() as u64);
+		self.absorb(data);
+	}
+
+	pub fn absorb_framed(&mut self, parts: &[&[u8]]) {
+		self.absorb_u64(parts.len() as u64);
+		for p in parts {
+			self.absorb_len_prefixed(p);
+		}
+	}
+
+	// absorb everything r yields, returning the byte count.
+	#[cfg(feature = "std")]
+	pub fn absorb_reader<R: Read>(&mut self, mut r: R) -> io::Result<u64> {
+		let mut buf = [0; READ_CHUNK];
+		let mut total = 0;
+		loop {
+			let n = match r.read(&mut buf) {
+				Ok(0) => return Ok(total),
+				Ok(n) => n,
+				Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+				Err(e) => return Err(e),
+			};
+			self.absorb(&buf[.. n]);
+			total += n as u64;
+			wipe_temp(&mut buf[.. n]);
+		}
+	}
+
+	pub fn absorb_stop(&mut self) {
+		self.sp.absorb_stop();
+	}
+
+	pub fn squeeze(&mut self, out: &mut [u8]) {
+		self.sp.squeeze(out);
+	}
+
+	// the keystream alone, for aead::Sealer and Opener, which do the
+	// absorbing themselves.
+	#[cfg(feature = "aead")]
+	pub(crate) fn apply_keystream(&mut self, buf: &mut [u8]) {
+		self.sp.apply_keystream(buf);
+	}
+
+	// encrypt buf in place.
+	pub fn seal(&mut self, buf: &mut [u8]) {
+		self.sp.absorb_stop();
+		for block in buf.chunks_mut(BLOCK) {
+			self.sp.apply_keystream(block);
+			self.sp.absorb(block);
+		}
+		self.absorb_stop();
+	}
+
+	// decrypt buf in place. the plaintext is unauthenticated until a tag
+	// squeezed afterwards has been checked with verify().
+	pub fn open(&mut self, buf: &mut [u8]) {
+		self.sp.absorb_stop();
+		for block in buf.chunks_mut(BLOCK) {
+			let mut ks = [0; BLOCK];
+			self.sp.squeeze(&mut ks[.. block.len()]);
+			self.sp.absorb(block);
+			for (v, k) in block.iter_mut().zip(ks.iter()) {
+				*v ^= k;
+			}
+			wipe_temp(&mut ks);
+		}
+		self.absorb_stop();
+	}
+
+	// see Spritz::wipe.
+	pub fn wipe(&mut self) {
+		self.sp.wipe();
+	}
+
+	// squeeze tag.len() bytes and compare them to tag in constant time,
+	// a block at a time so it doesn't need to allocate.
+	pub fn verify(&mut self, tag: &[u8]) -> bool {
+		let mut t = [0; BLOCK];
+		let mut ok = true;
+		for c in tag.chunks(BLOCK) {
+			self.squeeze(&mut t[.. c.len()]);
+			ok &= ct_eq(&t[.. c.len()], c);
+		}
+		wipe_temp(&mut t);
+		ok
+	}
+}
+
+#[test]
+fn duplex() {
+	let msg = [0x55; 150];
+	let mut a = SpritzDuplex::new(b"key");
+	a.absorb(b"header");
+	assert_eq!(a.phase(), Phase::Absorbing);
+	let mut buf = msg;
+	a.seal(&mut buf);
+	assert!(buf[..] != msg[..]);
+	let mut tag = [0; 32];
+	a.squeeze(&mut tag);
+	assert_eq!(a.phase(), Phase::Squeezing);
+
+	let mut b = SpritzDuplex::new(b"key");
+	b.absorb(b"header");
+	let mut ct = buf;
+	b.open(&mut ct);
+	assert_eq!(ct[..], msg[..]);
+	assert!(b.verify(&tag));
+
+	let mut c = SpritzDuplex::new(b"key");
+	c.absorb(b"header");
+	buf[149] ^= 1;
+	c.open(&mut buf);
+	assert!(!c.verify(&tag));
+}
+
+#[test]
+fn framed() {
+	let mut a = SpritzDuplex::new(b"");
+	let mut b = SpritzDuplex::new(b"");
+	a.absorb_framed(&[b"ab", b"c"]);
+	b.absorb_framed(&[b"a", b"bc"]);
+	let (mut x, mut y) = ([0; 16], [0; 16]);
+	a.squeeze(&mut x);
+	b.squeeze(&mut y);
+	assert!(x != y);
+
+	let mut c = SpritzDuplex::new(b"");
+	c.absorb_u64(2);
+	c.absorb_len_prefixed(b"a");
+	c.absorb_len_prefixed(b"bc");
+	c.squeeze(&mut x);
+	assert_eq!(x, y);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn absorb_reader() {
+	let data = vec![7; 10000];
+	let mut a = SpritzDuplex::new(b"");
+	let mut b = SpritzDuplex::new(b"");
+	assert_eq!(a.absorb_reader(&data[..]).unwrap(), 10000);
+	b.absorb(&data);
+	let (mut x, mut y) = ([0; 16], [0; 16]);
+	a.squeeze(&mut x);
+	b.squeeze(&mut y);
+	assert_eq!(x, y);
+}
@@ -0,0 +1,169 @@
+// hex and base64 (RFC 4648, padded) text encodings.
+//
+// decoding is strict: no whitespace, no missing padding, no non-canonical
+// trailing bits, so every byte string has exactly one encoding.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::str::FromStr;
+
+use fixed;
+use error::{Error, Result};
+#[cfg(feature = "hash")]
+use hash::{Digest, DIGEST_LEN};
+use key::{Key, Nonce, KEY_LEN, NONCE_LEN};
+#[cfg(feature = "mac")]
+use mac::{Tag, TAG_LEN};
+
+const HEX: &[u8; 16] = b"0123456789abcdef";
+const B64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn to_hex(data: &[u8]) -> String {
+	let mut s = String::with_capacity(data.len() * 2);
+	for b in data {
+		s.push(HEX[(b >> 4) as usize] as char);
+		s.push(HEX[(b & 0xf) as usize] as char);
+	}
+	s
+}
+
+fn hex_val(c: u8) -> Result<u8> {
+	match c {
+		b'0' ..= b'9' => Ok(c - b'0'),
+		b'a' ..= b'f' => Ok(c - b'a' + 10),
+		b'A' ..= b'F' => Ok(c - b'A' + 10),
+		_ => Err(Error::InvalidEncoding),
+	}
+}
+
+// either case is accepted.
+pub fn from_hex(s: &str) -> Result<Vec<u8>> {
+	let s = s.as_bytes();
+	if !s.len().is_multiple_of(2) {
+		return Err(Error::InvalidEncoding);
+	}
+	s.chunks(2).map(|c| Ok(hex_val(c[0])? << 4 | hex_val(c[1])?)).collect()
+}
+
+pub fn to_base64(data: &[u8]) -> String {
+	let mut s = String::with_capacity(data.len().div_ceil(3) * 4);
+	for c in data.chunks(3) {
+		let n = (c[0] as u32) << 16 | (*c.get(1).unwrap_or(&0) as u32) << 8 | *c.get(2).unwrap_or(&0) as u32;
+		for i in 0 .. 4 {
+			if i <= c.len() {
+				s.push(B64[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+			} else {
+				s.push('=');
+			}
+		}
+	}
+	s
+}
+
+fn b64_val(c: u8) -> Result<u32> {
+	match B64.iter().position(|&b| b == c) {
+		Some(v) => Ok(v as u32),
+		None => Err(Error::InvalidEncoding),
+	}
+}
+
+pub fn from_base64(s: &str) -> Result<Vec<u8>> {
+	let s = s.as_bytes();
+	if !s.len().is_multiple_of(4) {
+		return Err(Error::InvalidEncoding);
+	}
+	let mut out = Vec::with_capacity(s.len() / 4 * 3);
+	for (i, c) in s.chunks(4).enumerate() {
+		let last = i == s.len() / 4 - 1;
+		let pad = c.iter().rev().take_while(|&&b| b == b'=').count();
+		if pad > 2 || (pad > 0 && !last) {
+			return Err(Error::InvalidEncoding);
+		}
+		let mut n = 0;
+		for &b in &c[.. 4 - pad] {
+			n = n << 6 | b64_val(b)?;
+		}
+		n <<= 6 * pad as u32;
+		let bytes = [(n >> 16) as u8, (n >> 8) as u8, n as u8];
+		if bytes[3 - pad ..].iter().any(|&b| b != 0) {
+			return Err(Error::InvalidEncoding);
+		}
+		out.extend_from_slice(&bytes[.. 3 - pad]);
+	}
+	Ok(out)
+}
+
+macro_rules! encodings {
+	($t:ident, $len:expr) => {
+		impl $t {
+			pub fn to_hex(&self) -> String {
+				to_hex(self.as_bytes())
+			}
+
+			pub fn from_hex(s: &str) -> Result<$t> {
+				Ok($t::new(fixed::<{ $len }>(&from_hex(s)?)?))
+			}
+
+			pub fn to_base64(&self) -> String {
+				to_base64(self.as_bytes())
+			}
+
+			pub fn from_base64(s: &str) -> Result<$t> {
+				Ok($t::new(fixed::<{ $len }>(&from_base64(s)?)?))
+			}
+		}
+
+		// hex, or base64 if it isn't valid hex.
+		impl FromStr for $t {
+			type Err = Error;
+
+			fn from_str(s: &str) -> Result<$t> {
+				match $t::from_hex(s) {
+					Err(Error::InvalidEncoding) => $t::from_base64(s),
+					r => r,
+				}
+			}
+		}
+	};
+}
+
+encodings!(Key, KEY_LEN);
+encodings!(Nonce, NONCE_LEN);
+#[cfg(feature = "mac")]
+encodings!(Tag, TAG_LEN);
+#[cfg(feature = "hash")]
+encodings!(Digest, DIGEST_LEN);
+
+#[test]
+fn encoding() {
+	assert_eq!(to_hex(b"\x00\xffab"), "00ff6162");
+	assert_eq!(from_hex("00FF6162").unwrap(), b"\x00\xffab");
+	assert_eq!(from_hex("0"), Err(Error::InvalidEncoding));
+	assert_eq!(from_hex("zz"), Err(Error::InvalidEncoding));
+
+	// RFC 4648 section 10.
+	let v = ["", "Zg==", "Zm8=", "Zm9v", "Zm9vYg==", "Zm9vYmE=", "Zm9vYmFy"];
+	for (i, e) in v.iter().enumerate() {
+		assert_eq!(to_base64(&b"foobar"[.. i]), *e);
+		assert_eq!(from_base64(e).unwrap(), &b"foobar"[.. i]);
+	}
+	assert_eq!(from_base64("Zh=="), Err(Error::InvalidEncoding));
+	assert_eq!(from_base64("Zg="), Err(Error::InvalidEncoding));
+	assert_eq!(from_base64("Zg==Zg=="), Err(Error::InvalidEncoding));
+
+	let n = Nonce::from_counter(258);
+	assert_eq!(n.to_hex(), "00000000000000000000000000000102");
+	assert_eq!(Nonce::from_hex(&n.to_hex()).unwrap(), n);
+	assert_eq!(Nonce::from_base64(&n.to_base64()).unwrap(), n);
+	assert_eq!(Nonce::from_hex("00").err(), Some(Error::UnexpectedLength { expected: 16, actual: 1 }));
+	assert_eq!(n.to_hex().parse::<Nonce>().unwrap(), n);
+	assert_eq!(n.to_base64().parse::<Nonce>().unwrap(), n);
+	assert_eq!("xyz".parse::<Nonce>().err(), Some(Error::InvalidEncoding));
+}
+
+#[cfg(all(feature = "std", feature = "hash"))]
+#[test]
+fn digest_hex() {
+	let d = ::SpritzHasher::new().finalize();
+	assert_eq!(Digest::from_hex(&d.to_string()).unwrap(), d);
+}
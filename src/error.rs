@@ -0,0 +1,60 @@
+// errors returned by the higher level constructions.
+
+use core::fmt;
+use core::result;
+#[cfg(feature = "std")]
+use std::error;
+#[cfg(feature = "std")]
+use std::io;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Error {
+	InvalidTag,
+	InvalidLength,
+	UnexpectedLength { expected: usize, actual: usize },
+	UnexpectedSequence,
+	OutOfDomain,
+	Truncated,
+	InvalidEncoding,
+	BadChecksum,
+	Cancelled,
+	Exhausted,
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Error::InvalidTag => write!(f, "spritz: authentication tag mismatch"),
+			Error::InvalidLength => write!(f, "spritz: invalid length"),
+			Error::UnexpectedLength { expected, actual } => {
+				write!(f, "spritz: expected {} bytes, got {}", expected, actual)
+			}
+			Error::UnexpectedSequence => write!(f, "spritz: unexpected sequence number"),
+			Error::OutOfDomain => write!(f, "spritz: value outside the domain"),
+			Error::Truncated => write!(f, "spritz: truncated input"),
+			Error::InvalidEncoding => write!(f, "spritz: invalid hex or base64"),
+			Error::BadChecksum => write!(f, "spritz: armor checksum mismatch"),
+			Error::Cancelled => write!(f, "spritz: operation cancelled"),
+			Error::Exhausted => write!(f, "spritz: sequence numbers used up"),
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl error::Error for Error {}
+
+// for the io adapters. the Error can be recovered with get_ref/downcast_ref.
+// a cancellation isn't bad data, and must not be Interrupted or read loops
+// would just retry it.
+#[cfg(feature = "std")]
+impl From<Error> for io::Error {
+	fn from(e: Error) -> io::Error {
+		let kind = match e {
+			Error::Cancelled => io::ErrorKind::Other,
+			_ => io::ErrorKind::InvalidData,
+		};
+		io::Error::new(kind, e)
+	}
+}
@@ -0,0 +1,217 @@
+// a C ABI over the hash, MAC, stream cipher and AEAD, declared in
+// include/spritz.h. each mode is an opaque context from *_new, fed with
+// *_update and freed by *_finalize, which writes the result; a NULL out
+// pointer to *_finalize just frees the context. the stream cipher has no
+// result, so it has spritz_stream_free instead.
+//
+// NULL data pointers are fine with a length of 0. functions that can fail
+// return 0 on success and -1 otherwise, and *_new returns NULL on a bad
+// argument. a library to link from C is built with
+//
+//   cargo rustc --release --features ffi --crate-type staticlib
+//
+// or cdylib. the crate type isn't in Cargo.toml, since without std it
+// would need a panic handler.
+
+// the safety requirements are the header's: valid pointers and lengths, and
+// each context used from one thread and finalized once.
+#![allow(clippy::missing_safety_doc)]
+
+use std::boxed::Box;
+use std::os::raw::c_int;
+use std::ptr;
+use std::slice;
+
+use aead::{Opener, Sealer, SpritzAead, Tag, TAG_LEN};
+use cipher::Encryptor;
+use hash::{SpritzHasher, DIGEST_LEN};
+use key::{Key, Nonce, KEY_LEN, NONCE_LEN};
+use mac::SpritzMac;
+use Spritz;
+
+unsafe fn bytes<'a>(p: *const u8, len: usize) -> &'a [u8] {
+	if len == 0 {
+		&[]
+	} else {
+		slice::from_raw_parts(p, len)
+	}
+}
+
+unsafe fn bytes_mut<'a>(p: *mut u8, len: usize) -> &'a mut [u8] {
+	if len == 0 {
+		&mut []
+	} else {
+		slice::from_raw_parts_mut(p, len)
+	}
+}
+
+unsafe fn array<const L: usize>(p: *const u8) -> [u8; L] {
+	let mut a = [0; L];
+	a.copy_from_slice(slice::from_raw_parts(p, L));
+	a
+}
+
+fn boxed<T>(v: T) -> *mut T {
+	Box::into_raw(Box::new(v))
+}
+
+#[no_mangle]
+pub extern "C" fn spritz_hash_new() -> *mut SpritzHasher {
+	boxed(SpritzHasher::new())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn spritz_hash_update(ctx: *mut SpritzHasher, data: *const u8, len: usize) {
+	(*ctx).update(bytes(data, len));
+}
+
+// writes SPRITZ_DIGEST_LEN bytes.
+#[no_mangle]
+pub unsafe extern "C" fn spritz_hash_finalize(ctx: *mut SpritzHasher, out: *mut u8) {
+	let h = Box::from_raw(ctx);
+	if !out.is_null() {
+		ptr::copy_nonoverlapping(h.finalize().as_bytes().as_ptr(), out, DIGEST_LEN);
+	}
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn spritz_mac_new(key: *const u8, key_len: usize) -> *mut SpritzMac {
+	boxed(SpritzMac::new(bytes(key, key_len)))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn spritz_mac_update(ctx: *mut SpritzMac, data: *const u8, len: usize) {
+	(*ctx).update(bytes(data, len));
+}
+
+// writes SPRITZ_TAG_LEN bytes.
+#[no_mangle]
+pub unsafe extern "C" fn spritz_mac_finalize(ctx: *mut SpritzMac, out: *mut u8) {
+	let m = Box::from_raw(ctx);
+	if !out.is_null() {
+		ptr::copy_nonoverlapping(m.finalize().as_bytes().as_ptr(), out, TAG_LEN);
+	}
+}
+
+// the keystream of Spritz::with_raw_key(key).iv(iv), or of
+// deterministic() with iv_len 0.
+#[no_mangle]
+pub unsafe extern "C" fn spritz_stream_new(key: *const u8, key_len: usize, iv: *const u8, iv_len: usize) -> *mut Encryptor {
+	let k = Spritz::with_raw_key(bytes(key, key_len));
+	boxed(match iv_len {
+		0 => k.deterministic(),
+		_ => k.iv(bytes(iv, iv_len)),
+	})
+}
+
+// encrypts or decrypts buf in place, continuing the keystream.
+#[no_mangle]
+pub unsafe extern "C" fn spritz_stream_xor(ctx: *mut Encryptor, buf: *mut u8, len: usize) {
+	(*ctx).apply_keystream(bytes_mut(buf, len));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn spritz_stream_free(ctx: *mut Encryptor) {
+	if !ctx.is_null() {
+		let mut e = Box::from_raw(ctx);
+		e.sp.wipe();
+	}
+}
+
+unsafe fn aead(key: *const u8, nonce: *const u8) -> Option<(SpritzAead, Nonce)> {
+	if key.is_null() || nonce.is_null() {
+		return None;
+	}
+	let a = SpritzAead::new(&Key::new(array::<KEY_LEN>(key)));
+	Some((a, Nonce::new(array::<NONCE_LEN>(nonce))))
+}
+
+// key is SPRITZ_KEY_LEN bytes and nonce SPRITZ_NONCE_LEN.
+#[no_mangle]
+pub unsafe extern "C" fn spritz_seal_new(key: *const u8, nonce: *const u8, ad: *const u8, ad_len: usize) -> *mut Sealer {
+	match aead(key, nonce) {
+		Some((a, n)) => boxed(a.sealer(&n, bytes(ad, ad_len))),
+		None => ptr::null_mut(),
+	}
+}
+
+// encrypts buf in place.
+#[no_mangle]
+pub unsafe extern "C" fn spritz_seal_update(ctx: *mut Sealer, buf: *mut u8, len: usize) {
+	(*ctx).update(bytes_mut(buf, len));
+}
+
+// writes the SPRITZ_TAG_LEN byte tag.
+#[no_mangle]
+pub unsafe extern "C" fn spritz_seal_finalize(ctx: *mut Sealer, tag: *mut u8) {
+	let s = Box::from_raw(ctx);
+	if !tag.is_null() {
+		ptr::copy_nonoverlapping(s.finalize().as_bytes().as_ptr(), tag, TAG_LEN);
+	}
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn spritz_open_new(key: *const u8, nonce: *const u8, ad: *const u8, ad_len: usize) -> *mut Opener {
+	match aead(key, nonce) {
+		Some((a, n)) => boxed(a.opener(&n, bytes(ad, ad_len))),
+		None => ptr::null_mut(),
+	}
+}
+
+// decrypts buf in place. nothing decrypted may be used before
+// spritz_open_finalize returns 0.
+#[no_mangle]
+pub unsafe extern "C" fn spritz_open_update(ctx: *mut Opener, buf: *mut u8, len: usize) {
+	(*ctx).update(bytes_mut(buf, len));
+}
+
+// 0 if tag is right, -1 if it isn't or is NULL.
+#[no_mangle]
+pub unsafe extern "C" fn spritz_open_finalize(ctx: *mut Opener, tag: *const u8) -> c_int {
+	let o = Box::from_raw(ctx);
+	if tag.is_null() {
+		return -1;
+	}
+	match o.verify(&Tag::new(array::<TAG_LEN>(tag))) {
+		Ok(()) => 0,
+		Err(_) => -1,
+	}
+}
+
+#[test]
+fn ffi() {
+	unsafe {
+		let h = spritz_hash_new();
+		spritz_hash_update(h, b"AB".as_ptr(), 2);
+		spritz_hash_update(h, b"C".as_ptr(), 1);
+		spritz_hash_update(h, ptr::null(), 0);
+		let mut d = [0; DIGEST_LEN];
+		spritz_hash_finalize(h, d.as_mut_ptr());
+		assert_eq!(d[..], Spritz::hash256(b"ABC")[..]);
+
+		let s = spritz_stream_new(b"arcfour".as_ptr(), 7, ptr::null(), 0);
+		let mut buf = [0; 8];
+		spritz_stream_xor(s, buf.as_mut_ptr(), 8);
+		spritz_stream_free(s);
+		assert_eq!(buf[..], Spritz::new(b"arcfour").squeeze(8)[..]);
+
+		let (key, nonce) = ([5; KEY_LEN], [6; NONCE_LEN]);
+		let mut msg = *b"some plaintext";
+		let mut tag = [0; TAG_LEN];
+		let se = spritz_seal_new(key.as_ptr(), nonce.as_ptr(), b"ad".as_ptr(), 2);
+		spritz_seal_update(se, msg.as_mut_ptr(), msg.len());
+		spritz_seal_finalize(se, tag.as_mut_ptr());
+		let sealed = SpritzAead::new(&Key::new(key)).seal(&Nonce::new(nonce), b"ad", b"some plaintext");
+		assert_eq!(sealed[.. msg.len()], msg[..]);
+		assert_eq!(sealed[msg.len() ..], tag[..]);
+
+		let op = spritz_open_new(key.as_ptr(), nonce.as_ptr(), b"ad".as_ptr(), 2);
+		spritz_open_update(op, msg.as_mut_ptr(), msg.len());
+		assert_eq!(spritz_open_finalize(op, tag.as_ptr()), 0);
+		assert_eq!(&msg, b"some plaintext");
+		tag[0] ^= 1;
+		let op = spritz_open_new(key.as_ptr(), nonce.as_ptr(), ptr::null(), 0);
+		assert_eq!(spritz_open_finalize(op, tag.as_ptr()), -1);
+		assert!(spritz_seal_new(ptr::null(), nonce.as_ptr(), ptr::null(), 0).is_null());
+	}
+}
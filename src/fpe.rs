@@ -0,0 +1,189 @@
+// format-preserving encryption over [0, n).
+//
+// a balanced feistel network on the smallest even number of bits covering
+// n, with a keyed spritz hash as the round function, and cycle-walking: the
+// permutation is re-applied until the result lands back inside the domain.
+// since 2^bits < 4n that takes under four passes on average.
+//
+// strings are treated as fixed-length numbers in the radix of their
+// alphabet, so "0042" stays four digits.
+
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use error::{Error, Result};
+use Spritz;
+
+const ROUNDS: u8 = 10;
+pub const DIGITS: &str = "0123456789";
+
+#[derive(Clone)]
+pub struct Fpe {
+	keyed: Spritz,
+}
+
+fn domain_bits(n: u128) -> u32 {
+	let bits = 128 - (n - 1).leading_zeros();
+	(bits + 1) & !1
+}
+
+impl Fpe {
+	pub fn new(key: &[u8]) -> Fpe {
+		let mut sp = Spritz::new(key);
+		sp.absorb_stop();
+		sp.absorb(b"spritz fpe");
+		Fpe { keyed: sp }
+	}
+
+	fn round(&self, n: u128, tweak: &[u8], r: u8, x: u128, half: u32) -> u128 {
+		let mut sp = self.keyed.clone();
+		sp.absorb_stop();
+		sp.absorb(&n.to_be_bytes());
+		sp.absorb(&(tweak.len() as u64).to_be_bytes());
+		sp.absorb(tweak);
+		sp.absorb(&[r]);
+		sp.absorb(&x.to_be_bytes());
+		let mut b = [0; 16];
+		sp.squeeze_into(&mut b);
+		u128::from_be_bytes(b) & mask(half)
+	}
+
+	fn permute(&self, n: u128, x: u128, tweak: &[u8], inverse: bool) -> u128 {
+		let half = domain_bits(n) / 2;
+		let mut x = x;
+		loop {
+			let (mut l, mut r) = (x >> half, x & mask(half));
+			if !inverse {
+				for i in 0 .. ROUNDS {
+					let t = l ^ self.round(n, tweak, i, r, half);
+					l = r;
+					r = t;
+				}
+			} else {
+				for i in (0 .. ROUNDS).rev() {
+					let t = r ^ self.round(n, tweak, i, l, half);
+					r = l;
+					l = t;
+				}
+			}
+			x = (l << half) | r;
+			if x < n {
+				return x;
+			}
+		}
+	}
+
+	// encrypt x in [0, n).
+	pub fn encrypt(&self, n: u128, x: u128, tweak: &[u8]) -> Result<u128> {
+		if x >= n {
+			return Err(Error::OutOfDomain);
+		}
+		if n == 1 {
+			return Ok(0);
+		}
+		Ok(self.permute(n, x, tweak, false))
+	}
+
+	pub fn decrypt(&self, n: u128, y: u128, tweak: &[u8]) -> Result<u128> {
+		if y >= n {
+			return Err(Error::OutOfDomain);
+		}
+		if n == 1 {
+			return Ok(0);
+		}
+		Ok(self.permute(n, y, tweak, true))
+	}
+
+	pub fn encrypt_u32(&self, x: u32, tweak: &[u8]) -> u32 {
+		self.permute(1 << 32, x as u128, tweak, false) as u32
+	}
+
+	pub fn decrypt_u32(&self, y: u32, tweak: &[u8]) -> u32 {
+		self.permute(1 << 32, y as u128, tweak, true) as u32
+	}
+
+	// encrypt a string over alphabet, e.g. DIGITS for card numbers.
+	#[cfg(feature = "alloc")]
+	pub fn encrypt_str(&self, alphabet: &str, s: &str, tweak: &[u8]) -> Result<String> {
+		self.map_str(alphabet, s, tweak, false)
+	}
+
+	#[cfg(feature = "alloc")]
+	pub fn decrypt_str(&self, alphabet: &str, s: &str, tweak: &[u8]) -> Result<String> {
+		self.map_str(alphabet, s, tweak, true)
+	}
+
+	#[cfg(feature = "alloc")]
+	fn map_str(&self, alphabet: &str, s: &str, tweak: &[u8], inverse: bool) -> Result<String> {
+		let chars: Vec<char> = alphabet.chars().collect();
+		let radix = chars.len() as u128;
+		if radix < 2 {
+			return Err(Error::OutOfDomain);
+		}
+		// a repeated character would decode to its first place, so the
+		// second one couldn't round trip.
+		if chars.iter().enumerate().any(|(i, c)| chars[.. i].contains(c)) {
+			return Err(Error::InvalidEncoding);
+		}
+		let len = s.chars().count();
+		let mut n: u128 = 1;
+		let mut x: u128 = 0;
+		for c in s.chars() {
+			let d = chars.iter().position(|a| *a == c).ok_or(Error::OutOfDomain)?;
+			n = n.checked_mul(radix).ok_or(Error::InvalidLength)?;
+			x = x * radix + d as u128;
+		}
+		if len == 0 {
+			return Ok(String::new());
+		}
+
+		let mut tw = Vec::with_capacity(tweak.len() + alphabet.len() + 8);
+		tw.extend_from_slice(&(alphabet.len() as u64).to_be_bytes());
+		tw.extend_from_slice(alphabet.as_bytes());
+		tw.extend_from_slice(tweak);
+		let mut y = if inverse { self.decrypt(n, x, &tw)? } else { self.encrypt(n, x, &tw)? };
+
+		let mut out = vec![chars[0]; len];
+		for v in out.iter_mut().rev() {
+			*v = chars[(y % radix) as usize];
+			y /= radix;
+		}
+		Ok(out.into_iter().collect())
+	}
+}
+
+fn mask(bits: u32) -> u128 {
+	if bits >= 128 {
+		!0
+	} else {
+		(1 << bits) - 1
+	}
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn fpe() {
+	let f = Fpe::new(b"key");
+	for &n in &[2u128, 10, 1000, 1 << 20, 12345] {
+		for x in 0 .. 5.min(n) {
+			let y = f.encrypt(n, x, b"t").unwrap();
+			assert!(y < n);
+			assert_eq!(f.decrypt(n, y, b"t").unwrap(), x);
+		}
+	}
+	assert_eq!(f.encrypt(10, 10, b""), Err(Error::OutOfDomain));
+
+	let y = f.encrypt_u32(42, b"");
+	assert_eq!(f.decrypt_u32(y, b""), 42);
+
+	let card = "4111111111111111";
+	let c = f.encrypt_str(DIGITS, card, b"").unwrap();
+	assert_eq!(c.len(), 16);
+	assert!(c != card && c.chars().all(|c| c.is_ascii_digit()));
+	assert_eq!(f.decrypt_str(DIGITS, &c, b"").unwrap(), card);
+	assert_eq!(f.encrypt_str(DIGITS, "12a", b""), Err(Error::OutOfDomain));
+	assert_eq!(f.encrypt_str("01231", "1", b""), Err(Error::InvalidEncoding));
+	assert_eq!(f.decrypt_str("aa", "", b""), Err(Error::InvalidEncoding));
+}
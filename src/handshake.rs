@@ -0,0 +1,117 @@
+// pre-shared key handshake that sets up a SecureChannel.
+//
+//   initiator -> responder: ni || mac1
+//   responder -> initiator: nr || mac2
+//   initiator -> responder: mac3
+//
+// every mac covers the psk and all messages so far, so each side proves it
+// knows the psk. the responder only accepts mac3 for its own fresh nr and
+// the initiator only accepts mac2 for its own fresh ni, so replayed
+// messages from an earlier run are rejected. the session key and id are
+// squeezed from the final state.
+
+use alloc::vec::Vec;
+
+use channel::{Role, SecureChannel};
+use error::{Error, Result};
+use protocol::Protocol;
+use rng::SpritzRng;
+use wipe_temp;
+
+pub const NONCE_LEN: usize = 16;
+const MAC_LEN: usize = 16;
+const SESSION_KEY_LEN: usize = 32;
+const SESSION_ID_LEN: usize = 16;
+
+fn start(psk: &[u8]) -> Protocol {
+	let mut p = Protocol::new(b"spritz psk handshake");
+	p.key(psk);
+	p
+}
+
+fn split(msg: &[u8]) -> Result<(&[u8], &[u8])> {
+	if msg.len() != NONCE_LEN + MAC_LEN {
+		return Err(Error::InvalidLength);
+	}
+	Ok(msg.split_at(NONCE_LEN))
+}
+
+fn channel(mut p: Protocol, role: Role) -> SecureChannel {
+	let mut key = [0; SESSION_KEY_LEN];
+	let mut id = [0; SESSION_ID_LEN];
+	p.prf(&mut key);
+	p.prf(&mut id);
+	let c = SecureChannel::new(&key, &id, role);
+	wipe_temp(&mut key);
+	c
+}
+
+pub struct Initiator {
+	p: Protocol,
+}
+
+impl Initiator {
+	// returns the state and the first message.
+	pub fn new(psk: &[u8], rng: &mut SpritzRng) -> (Initiator, Vec<u8>) {
+		let mut p = start(psk);
+		let mut msg = vec![0; NONCE_LEN + MAC_LEN];
+		rng.fill_bytes(&mut msg[.. NONCE_LEN]);
+		p.ad(&msg[.. NONCE_LEN]);
+		p.send_mac(&mut msg[NONCE_LEN ..]);
+		(Initiator { p }, msg)
+	}
+
+	// takes the responder's message, returns the channel and the last message.
+	pub fn finish(mut self, msg: &[u8]) -> Result<(SecureChannel, Vec<u8>)> {
+		let (nr, mac) = split(msg)?;
+		self.p.ad(nr);
+		self.p.recv_mac(mac)?;
+		let mut confirm = vec![0; MAC_LEN];
+		self.p.send_mac(&mut confirm);
+		Ok((channel(self.p, Role::Initiator), confirm))
+	}
+}
+
+pub struct Responder {
+	p: Protocol,
+}
+
+impl Responder {
+	// takes the initiator's first message, returns the state and a reply.
+	pub fn new(psk: &[u8], msg: &[u8], rng: &mut SpritzRng) -> Result<(Responder, Vec<u8>)> {
+		let (ni, mac) = split(msg)?;
+		let mut p = start(psk);
+		p.ad(ni);
+		p.recv_mac(mac)?;
+		let mut reply = vec![0; NONCE_LEN + MAC_LEN];
+		rng.fill_bytes(&mut reply[.. NONCE_LEN]);
+		p.ad(&reply[.. NONCE_LEN]);
+		p.send_mac(&mut reply[NONCE_LEN ..]);
+		Ok((Responder { p }, reply))
+	}
+
+	pub fn finish(mut self, msg: &[u8]) -> Result<SecureChannel> {
+		if msg.len() != MAC_LEN {
+			return Err(Error::InvalidLength);
+		}
+		self.p.recv_mac(msg)?;
+		Ok(channel(self.p, Role::Responder))
+	}
+}
+
+#[test]
+fn handshake() {
+	let mut rng = SpritzRng::new(b"test");
+	let (i, m1) = Initiator::new(b"psk", &mut rng);
+	let (r, m2) = Responder::new(b"psk", &m1, &mut rng).unwrap();
+	let (mut ci, m3) = i.finish(&m2).unwrap();
+	let mut cr = r.finish(&m3).unwrap();
+	assert_eq!(cr.recv(&ci.send(b"ping").unwrap()).unwrap(), b"ping");
+	assert_eq!(ci.recv(&cr.send(b"pong").unwrap()).unwrap(), b"pong");
+
+	assert_eq!(Responder::new(b"wrong", &m1, &mut rng).err(), Some(Error::InvalidTag));
+
+	// a replayed first message gets a fresh nr, so the old mac3 is useless.
+	let (r, _) = Responder::new(b"psk", &m1, &mut rng).unwrap();
+	assert_eq!(r.finish(&m3).err(), Some(Error::InvalidTag));
+}
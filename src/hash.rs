@@ -0,0 +1,263 @@
+// incremental hashing.
+//
+// Spritz hash absorbs the output length before squeezing, so a 32 byte
+// digest is not a prefix of a 64 byte one. the XOF instead absorbs a length
+// of zero, which no fixed-length hash uses, and can then be read for as
+// long as needed.
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+#[cfg(feature = "std")]
+use std::thread;
+
+use error::Error;
+use sponge::SpongePermutation;
+use {ct_eq, fixed, Spritz};
+
+pub const DIGEST_LEN: usize = 32;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Digest([u8; DIGEST_LEN]);
+
+impl Digest {
+	// == is constant time as well; this is just explicit about it.
+	pub fn ct_eq(&self, other: &Digest) -> bool {
+		ct_eq(&self.0, &other.0)
+	}
+
+	pub fn new(bytes: [u8; DIGEST_LEN]) -> Digest {
+		Digest(bytes)
+	}
+
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.0
+	}
+}
+
+impl TryFrom<&[u8]> for Digest {
+	type Error = Error;
+
+	fn try_from(v: &[u8]) -> Result<Digest, Error> {
+		fixed(v).map(Digest)
+	}
+}
+
+impl PartialEq for Digest {
+	fn eq(&self, other: &Digest) -> bool {
+		self.ct_eq(other)
+	}
+}
+
+impl Eq for Digest {}
+
+impl fmt::Display for Digest {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for b in &self.0 {
+			write!(f, "{:02x}", b)?;
+		}
+		Ok(())
+	}
+}
+
+// the one-shot hash from section 4 of the paper.
+impl Spritz {
+	#[cfg(feature = "alloc")]
+	pub fn hash256(msg: &[u8]) -> Vec<u8> {
+		Spritz::hash_array::<32>(msg).to_vec()
+	}
+
+	// the hash from the paper with r = out.len(), which is absorbed as one
+	// byte and so must be under 256.
+	pub fn hash_into(msg: &[u8], out: &mut [u8]) -> Result<(), Error> {
+		if out.len() > u8::MAX as usize {
+			return Err(Error::InvalidLength);
+		}
+		Spritz::hash_to(msg, out);
+		Ok(())
+	}
+
+	// hash_into for callers that have already checked out.len().
+	pub(crate) fn hash_to(msg: &[u8], out: &mut [u8]) {
+		let mut sp = Spritz::initialize_state();
+		sp.absorb(msg);
+		sp.absorb_stop();
+		sp.absorb(&[out.len() as u8]);
+		sp.squeeze_into(out);
+	}
+
+	// hash_into with the output length fixed at compile time, for callers
+	// without a heap. R over 255 fails to build.
+	pub fn hash_array<const R: usize>(msg: &[u8]) -> [u8; R] {
+		const { assert!(R <= u8::MAX as usize, "spritz: hash output over 255 bytes") };
+		let mut out = [0; R];
+		Spritz::hash_to(msg, &mut out);
+		out
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct SpritzHasher<P = Spritz> {
+	sp: P,
+}
+
+impl Default for SpritzHasher {
+	fn default() -> SpritzHasher {
+		SpritzHasher::new()
+	}
+}
+
+impl SpritzHasher {
+	pub fn new() -> SpritzHasher {
+		SpritzHasher::with_permutation()
+	}
+}
+
+impl<P: SpongePermutation> SpritzHasher<P> {
+	pub fn with_permutation() -> SpritzHasher<P> {
+		SpritzHasher { sp: P::initialize() }
+	}
+
+	pub fn update(&mut self, data: &[u8]) {
+		self.sp.absorb(data);
+	}
+
+	// same as Spritz::hash256 over everything passed to update.
+	pub fn finalize(mut self) -> Digest {
+		self.sp.absorb_stop();
+		self.sp.absorb(&[DIGEST_LEN as u8]);
+		let mut d = [0; DIGEST_LEN];
+		self.sp.squeeze(&mut d);
+		Digest(d)
+	}
+
+	// same as Spritz::hash_array, for digests other than DIGEST_LEN bytes.
+	pub fn finalize_array<const R: usize>(mut self) -> [u8; R] {
+		const { assert!(R <= u8::MAX as usize, "spritz: hash output over 255 bytes") };
+		self.sp.absorb_stop();
+		self.sp.absorb(&[R as u8]);
+		let mut d = [0; R];
+		self.sp.squeeze(&mut d);
+		d
+	}
+
+	pub fn finalize_xof(mut self) -> SpritzXofReader<P> {
+		self.sp.absorb_stop();
+		self.sp.absorb(&[0]);
+		SpritzXofReader { sp: self.sp }
+	}
+}
+
+#[cfg(feature = "std")]
+impl<P: SpongePermutation> Write for SpritzHasher<P> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.update(buf);
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+pub struct SpritzXofReader<P = Spritz> {
+	sp: P,
+}
+
+impl<P: SpongePermutation> SpritzXofReader<P> {
+	pub fn read(&mut self, out: &mut [u8]) {
+		self.sp.squeeze(out);
+	}
+}
+
+#[cfg(feature = "alloc")]
+pub fn hash_batch(msgs: &[&[u8]]) -> Vec<Digest> {
+	msgs.iter().map(|m| digest(m)).collect()
+}
+
+// hash_batch with an r byte hash of each message, written one after
+// another into out, which must be msgs.len() * r long.
+pub fn hash_batch_into(msgs: &[&[u8]], r: usize, out: &mut [u8]) -> Result<(), Error> {
+	if r > u8::MAX as usize {
+		return Err(Error::InvalidLength);
+	}
+	if out.len() != msgs.len() * r {
+		return Err(Error::UnexpectedLength { expected: msgs.len() * r, actual: out.len() });
+	}
+	if r == 0 {
+		return Ok(());
+	}
+	for (d, m) in out.chunks_exact_mut(r).zip(msgs.iter()) {
+		Spritz::hash_to(m, d);
+	}
+	Ok(())
+}
+
+// the same as hash_batch, split across threads.
+#[cfg(feature = "std")]
+pub fn hash_batch_parallel(msgs: &[&[u8]], threads: usize) -> Vec<Digest> {
+	let mut out = vec![Digest([0; DIGEST_LEN]); msgs.len()];
+	let per = msgs.len().div_ceil(threads.max(1)).max(1);
+	thread::scope(|s| {
+		for (dst, src) in out.chunks_mut(per).zip(msgs.chunks(per)) {
+			s.spawn(move || {
+				for (d, m) in dst.iter_mut().zip(src.iter()) {
+					*d = digest(m);
+				}
+			});
+		}
+	});
+	out
+}
+
+#[cfg(feature = "alloc")]
+fn digest(m: &[u8]) -> Digest {
+	Digest(Spritz::hash_array(m))
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn hasher() {
+	let mut h = SpritzHasher::new();
+	h.update(b"ar");
+	h.update(b"cfour");
+	assert_eq!(h.finalize().as_bytes(), &Spritz::hash256(b"arcfour")[..]);
+	assert!(SpritzHasher::new().finalize().to_string().len() == 2 * DIGEST_LEN);
+	let mut h = SpritzHasher::new();
+	h.update(b"arcfour");
+	assert_eq!(h.finalize_array::<20>(), Spritz::hash_array::<20>(b"arcfour"));
+
+	let mut x = SpritzHasher::new();
+	x.write_all(b"arcfour").unwrap();
+	let mut r = x.finalize_xof();
+	let (mut a, mut b) = ([0; 16], [0; 16]);
+	r.read(&mut a);
+	r.read(&mut b);
+	assert!(a != b);
+	assert!(a[..] != Spritz::hash256(b"arcfour")[.. 16]);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn batch() {
+	let msgs: Vec<&[u8]> = vec![b"ABC", b"spam", b"", b"arcfour", b"x"];
+	let d = hash_batch(&msgs);
+	for (d, m) in d.iter().zip(msgs.iter()) {
+		assert_eq!(d.as_bytes(), &Spritz::hash256(m)[..]);
+	}
+	assert_eq!(hash_batch_parallel(&msgs, 2), d);
+	assert_eq!(hash_batch_parallel(&msgs, 16), d);
+	assert!(hash_batch_parallel(&[], 4).is_empty());
+
+	let mut out = vec![0; msgs.len() * 20];
+	hash_batch_into(&msgs, 20, &mut out).unwrap();
+	for (d, m) in out.chunks(20).zip(msgs.iter()) {
+		assert_eq!(d, Spritz::hash_array::<20>(m));
+	}
+	assert_eq!(hash_batch_into(&msgs, 20, &mut out[1 ..]), Err(Error::UnexpectedLength { expected: 100, actual: 99 }));
+	assert_eq!(hash_batch_into(&msgs, 256, &mut []), Err(Error::InvalidLength));
+	assert_eq!(hash_batch_into(&msgs, 0, &mut []), Ok(()));
+}
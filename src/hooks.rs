@@ -0,0 +1,90 @@
+// callbacks on the steps inside the sponge, for tools that animate or trace
+// what the cipher does on a given input. a hook is a plain fn, so a state
+// with one stays Clone and Send; it gets the state after each step and what
+// happened in it.
+//
+// with a hook set, absorb goes a nibble at a time and squeeze_into a drip at
+// a time, so every step is seen; without one, and without the feature, the
+// fast paths are unchanged. a hook sees secret state, and its calls depend
+// on the key: it's for teaching and debugging, never for real keys.
+
+use Spritz;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Event {
+	// S[a] and S[N/2 + x] were swapped.
+	AbsorbNibble { a: u8, x: u8 },
+	// a shuffle is starting; the whips and crushes in it follow.
+	Shuffle,
+	// r updates were made, with w as it was before whip stepped it.
+	Whip { r: usize, w: u8 },
+	Crush,
+	// update() and output() gave z, with the registers after them.
+	Drip { i: u8, j: u8, k: u8, z: u8 },
+}
+
+pub type Hook = fn(&Spritz, Event);
+
+impl Spritz {
+	pub fn set_hook(&mut self, hook: Option<Hook>) {
+		self.hook = hook;
+	}
+
+	pub(crate) fn emit(&self, e: Event) {
+		if let Some(h) = self.hook {
+			h(self, e);
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn hooks() {
+	use std::cell::RefCell;
+	use std::vec::Vec;
+
+	thread_local!(static SEEN: RefCell<Vec<Event>> = const { RefCell::new(Vec::new()) });
+	fn record(_: &Spritz, e: Event) {
+		SEEN.with(|s| s.borrow_mut().push(e));
+	}
+
+	let mut sp = Spritz::initialize_state();
+	sp.set_hook(Some(record));
+	sp.absorb(&[0x21]);
+	let mut out = [0; 2];
+	sp.squeeze_into(&mut out);
+	assert_eq!(out[..], Spritz::new(&[0x21]).squeeze(2)[..]);
+
+	let seen = SEEN.with(|s| s.borrow().clone());
+	assert_eq!(seen[.. 3], [Event::AbsorbNibble { a: 0, x: 1 }, Event::AbsorbNibble { a: 1, x: 2 }, Event::Shuffle]);
+	assert_eq!(seen[3], Event::Whip { r: 512, w: 1 });
+	assert_eq!(seen.iter().filter(|e| **e == Event::Crush).count(), 2);
+	assert_eq!(seen.len(), 3 + 5 + 2);
+	assert_eq!(seen[9], Event::Drip { i: sp.i, j: sp.j, k: sp.k, z: out[1] });
+}
+
+// ratchet rebuilds the state, and the hook has to survive it.
+#[cfg(feature = "std")]
+#[test]
+fn ratchet_keeps_hook() {
+	use std::cell::Cell;
+
+	thread_local!(static DRIPS: Cell<usize> = const { Cell::new(0) });
+	fn count(_: &Spritz, e: Event) {
+		if let Event::Drip { .. } = e {
+			DRIPS.with(|d| d.set(d.get() + 1));
+		}
+	}
+
+	let mut sp = Spritz::new(b"key");
+	let mut plain = sp.clone();
+	sp.set_hook(Some(count));
+	sp.ratchet();
+	plain.ratchet();
+	let seen = DRIPS.with(|d| d.get());
+	assert!(seen > 0);
+	let mut out = [0; 4];
+	sp.squeeze_into(&mut out);
+	assert_eq!(DRIPS.with(|d| d.get()), seen + 4);
+	assert_eq!(out, plain.squeeze_array::<4>());
+}
@@ -0,0 +1,68 @@
+// password-based key derivation.
+//
+// the salt and password are absorbed framed, then every iteration squeezes
+// a block and absorbs it back, which costs a full shuffle each time and
+// can't be done in parallel. calibrate picks the iteration count for a
+// time budget on the current machine; store it with the salt.
+
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
+
+use duplex::SpritzDuplex;
+use key::{Key, KEY_LEN};
+use wipe_temp;
+
+pub const SALT_LEN: usize = 16;
+pub const MIN_ITERATIONS: u32 = 1000;
+// a stored count is read before anything can be checked, so readers refuse
+// counts over this rather than run for as long as the file asks. it is
+// seconds of work on current machines, far past what calibrate picks.
+pub const MAX_ITERATIONS: u32 = 1 << 20;
+
+const BLOCK: usize = 32;
+#[cfg(feature = "std")]
+const CALIBRATION_ROUNDS: u32 = 256;
+
+pub fn pbkdf(password: &[u8], salt: &[u8], iterations: u32) -> Key {
+	let mut d = SpritzDuplex::new(b"spritz pbkdf");
+	d.absorb_framed(&[salt, password]);
+	d.absorb_u64(iterations as u64);
+	let mut block = [0; BLOCK];
+	for _ in 0 .. iterations {
+		d.squeeze(&mut block);
+		d.absorb_stop();
+		d.absorb(&block);
+	}
+	wipe_temp(&mut block);
+	d.absorb_stop();
+	let mut k = [0; KEY_LEN];
+	d.squeeze(&mut k);
+	Key::new(k)
+}
+
+// iterations taking about target on this machine, between MIN_ITERATIONS
+// and MAX_ITERATIONS.
+#[cfg(feature = "std")]
+pub fn calibrate(target: Duration) -> u32 {
+	let start = Instant::now();
+	pbkdf(b"", &[0; SALT_LEN], CALIBRATION_ROUNDS);
+	let per = start.elapsed().as_secs_f64() / CALIBRATION_ROUNDS as f64;
+	let n = target.as_secs_f64() / per.max(1e-9);
+	(n.min(MAX_ITERATIONS as f64) as u32).max(MIN_ITERATIONS)
+}
+
+#[test]
+fn pbkdf_deterministic() {
+	let a = pbkdf(b"password", b"salt", 10);
+	assert_eq!(a.as_bytes(), pbkdf(b"password", b"salt", 10).as_bytes());
+	assert!(a.as_bytes() != pbkdf(b"password", b"salt", 11).as_bytes());
+	assert!(a.as_bytes() != pbkdf(b"password", b"SALT", 10).as_bytes());
+	assert!(pbkdf(b"ab", b"c", 10).as_bytes() != pbkdf(b"a", b"bc", 10).as_bytes());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn calibrated() {
+	assert!(calibrate(Duration::from_millis(1)) >= MIN_ITERATIONS);
+	assert_eq!(calibrate(Duration::from_secs(1 << 20)), MAX_ITERATIONS);
+}
@@ -0,0 +1,248 @@
+// keys, nonces and key fingerprints.
+
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+use core::convert::TryFrom;
+use core::fmt;
+
+use error::Error;
+#[cfg(feature = "mac")]
+use mac::SpritzMac;
+#[cfg(feature = "rng")]
+use rng::SpritzRng;
+use {fixed, Spritz};
+
+pub const KEY_LEN: usize = 32;
+pub const NONCE_LEN: usize = 16;
+pub const FINGERPRINT_LEN: usize = 16;
+
+#[derive(Clone)]
+pub struct Key([u8; KEY_LEN]);
+
+impl Key {
+	pub fn new(bytes: [u8; KEY_LEN]) -> Key {
+		Key(bytes)
+	}
+
+	#[cfg(feature = "rng")]
+	pub fn generate(rng: &mut SpritzRng) -> Key {
+		let mut k = [0; KEY_LEN];
+		rng.fill_bytes(&mut k);
+		Key(k)
+	}
+
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.0
+	}
+
+	// a short digest of the key for comparing keys out-of-band. the label
+	// keeps it from being a prefix of hash256 of the raw key.
+	pub fn fingerprint(&self) -> Fingerprint {
+		let mut sp = Spritz::initialize_state();
+		sp.absorb(b"spritz key fingerprint");
+		sp.absorb_stop();
+		sp.absorb(&self.0);
+		sp.absorb_stop();
+		sp.absorb(&[FINGERPRINT_LEN as u8]);
+		let mut fp = [0; FINGERPRINT_LEN];
+		sp.squeeze_into(&mut fp);
+		Fingerprint(fp)
+	}
+}
+
+// nonces aren't wiped: they're Copy and usually travel in the clear anyway.
+#[cfg(feature = "zeroize")]
+impl Drop for Key {
+	fn drop(&mut self) {
+		::zeroize(&mut self.0);
+	}
+}
+
+impl From<[u8; KEY_LEN]> for Key {
+	fn from(bytes: [u8; KEY_LEN]) -> Key {
+		Key::new(bytes)
+	}
+}
+
+// must never repeat under the same key.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Nonce([u8; NONCE_LEN]);
+
+impl Nonce {
+	pub fn new(bytes: [u8; NONCE_LEN]) -> Nonce {
+		Nonce(bytes)
+	}
+
+	#[cfg(feature = "rng")]
+	pub fn generate(rng: &mut SpritzRng) -> Nonce {
+		let mut n = [0; NONCE_LEN];
+		rng.fill_bytes(&mut n);
+		Nonce(n)
+	}
+
+	// a counter nonce, for when the caller already keeps unique sequence numbers.
+	pub fn from_counter(n: u64) -> Nonce {
+		let mut b = [0; NONCE_LEN];
+		b[NONCE_LEN - 8 ..].copy_from_slice(&n.to_be_bytes());
+		Nonce(b)
+	}
+
+	// the nonce for item n of a sequence sealed under one base nonce.
+	pub fn xor_counter(&self, n: u64) -> Nonce {
+		let mut b = self.0;
+		for (v, c) in b[NONCE_LEN - 8 ..].iter_mut().zip(n.to_be_bytes().iter()) {
+			*v ^= c;
+		}
+		Nonce(b)
+	}
+
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.0
+	}
+}
+
+// the nonce for message counter of a session, so senders that each have
+// their own session id need no shared counter. it's the MAC under key of
+// the id and counter, so nonces only repeat if a (session_id, counter) pair
+// does, or by a 128-bit collision, and can't be predicted without the key.
+// session ids must be unique per key.
+#[cfg(feature = "mac")]
+pub fn derive_nonce(key: &Key, session_id: &[u8], counter: u64) -> Nonce {
+	let mut m = SpritzMac::new(key.as_bytes());
+	m.update(b"spritz nonce");
+	m.update(session_id);
+	// the counter is fixed length, so where the id ends is unambiguous.
+	m.update(&counter.to_be_bytes());
+	Nonce(m.finalize_array())
+}
+
+impl TryFrom<&[u8]> for Key {
+	type Error = Error;
+
+	fn try_from(v: &[u8]) -> Result<Key, Error> {
+		fixed(v).map(Key)
+	}
+}
+
+impl TryFrom<&[u8]> for Nonce {
+	type Error = Error;
+
+	fn try_from(v: &[u8]) -> Result<Nonce, Error> {
+		fixed(v).map(Nonce)
+	}
+}
+
+impl From<[u8; NONCE_LEN]> for Nonce {
+	fn from(bytes: [u8; NONCE_LEN]) -> Nonce {
+		Nonce::new(bytes)
+	}
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Fingerprint([u8; FINGERPRINT_LEN]);
+
+#[cfg(feature = "alloc")]
+const FIELD_X: usize = 17;
+#[cfg(feature = "alloc")]
+const FIELD_Y: usize = 9;
+
+impl Fingerprint {
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.0
+	}
+
+	// the "drunken bishop" walk from OpenSSH's key.c: every byte is four
+	// 2-bit moves, low bits first, and cells count how often they're visited.
+	#[cfg(feature = "alloc")]
+	pub fn randomart(&self) -> String {
+		let symbols = b" .o+=*BOX@%&#/^SE";
+		let top = symbols.len() as u8 - 1;
+		let mut field = [[0u8; FIELD_Y]; FIELD_X];
+		let (mut x, mut y) = (FIELD_X / 2, FIELD_Y / 2);
+		let start = (x, y);
+		for b in &self.0 {
+			let mut input = *b;
+			for _ in 0 .. 4 {
+				x = if input & 1 != 0 { (x + 1).min(FIELD_X - 1) } else { x.saturating_sub(1) };
+				y = if input & 2 != 0 { (y + 1).min(FIELD_Y - 1) } else { y.saturating_sub(1) };
+				if field[x][y] < top - 2 {
+					field[x][y] += 1;
+				}
+				input >>= 2;
+			}
+		}
+		field[start.0][start.1] = top - 1;
+		field[x][y] = top;
+
+		let title = "[SPRITZ]";
+		let pad = FIELD_X - title.len();
+		let mut s = String::new();
+		s.push('+');
+		s.push_str(&"-".repeat(pad / 2));
+		s.push_str(title);
+		s.push_str(&"-".repeat(pad - pad / 2));
+		s.push_str("+\n");
+		for row in 0 .. FIELD_Y {
+			s.push('|');
+			for col in field.iter() {
+				s.push(symbols[col[row] as usize] as char);
+			}
+			s.push_str("|\n");
+		}
+		s.push('+');
+		s.push_str(&"-".repeat(FIELD_X));
+		s.push('+');
+		s
+	}
+}
+
+impl fmt::Display for Fingerprint {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for (i, b) in self.0.iter().enumerate() {
+			if i > 0 {
+				write!(f, ":")?;
+			}
+			write!(f, "{:02x}", b)?;
+		}
+		Ok(())
+	}
+}
+
+#[test]
+fn try_from() {
+	assert!(Key::try_from(&[0; KEY_LEN][..]).is_ok());
+	assert_eq!(Nonce::try_from(&[1; 4][..]).err(), Some(Error::UnexpectedLength { expected: NONCE_LEN, actual: 4 }));
+}
+
+#[cfg(feature = "mac")]
+#[test]
+fn derived_nonces() {
+	let key = Key::new([1; KEY_LEN]);
+	let a = derive_nonce(&key, b"sender a", 1);
+	assert_eq!(a, derive_nonce(&key, b"sender a", 1));
+	assert!(a != derive_nonce(&key, b"sender a", 2));
+	assert!(a != derive_nonce(&key, b"sender b", 1));
+	assert!(a != derive_nonce(&Key::new([2; KEY_LEN]), b"sender a", 1));
+	assert!(derive_nonce(&key, b"", 0) != Nonce::from_counter(0));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn fingerprint() {
+	let a = Key::new([1; KEY_LEN]).fingerprint();
+	let b = Key::new([2; KEY_LEN]).fingerprint();
+	assert!(a != b);
+	assert_eq!(a, Key::new([1; KEY_LEN]).fingerprint());
+	assert_eq!(a.to_string().len(), FINGERPRINT_LEN * 3 - 1);
+
+	let art = a.randomart();
+	let lines: Vec<&str> = art.lines().collect();
+	assert_eq!(lines.len(), FIELD_Y + 2);
+	for l in &lines {
+		assert_eq!(l.len(), FIELD_X + 2);
+	}
+	assert!(lines[0].contains("[SPRITZ]"));
+	let center = lines[1 + FIELD_Y / 2].as_bytes()[1 + FIELD_X / 2];
+	assert!(center == b'S' || center == b'E');
+	assert!(art.contains('E'));
+}
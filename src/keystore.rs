@@ -0,0 +1,185 @@
+// named keys kept in a file under a passphrase.
+//
+//   magic (8) || iterations (4) || salt (16) || nonce (16) || sealed keys
+//
+// the sealing key comes from the pbkdf over the passphrase and salt, and
+// the header is the associated data, so a lowered iteration count or a
+// swapped salt fails the tag like any other change. the keys are
+//
+//   count (4) || (name length (4) || name || key (32)) for each
+//
+// and a wrong passphrase can't be told apart from a damaged file.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use aead::SpritzAead;
+use error::{Error, Result};
+use kdf::{calibrate, pbkdf, MAX_ITERATIONS, MIN_ITERATIONS, SALT_LEN};
+use key::{Key, Nonce, KEY_LEN, NONCE_LEN};
+use rng::SpritzRng;
+use zeroize;
+
+const MAGIC: &[u8; 8] = b"SPZKEY\x00\x01";
+const HEADER_LEN: usize = 8 + 4 + SALT_LEN;
+const SAVE_TIME: Duration = Duration::from_millis(250);
+
+#[derive(Clone, Default)]
+pub struct Keystore {
+	keys: BTreeMap<String, Key>,
+}
+
+fn take<'a>(buf: &mut &'a [u8], n: usize) -> Result<&'a [u8]> {
+	if buf.len() < n {
+		return Err(Error::Truncated);
+	}
+	let (a, b) = buf.split_at(n);
+	*buf = b;
+	Ok(a)
+}
+
+fn take_u32(buf: &mut &[u8]) -> Result<u32> {
+	let mut b = [0; 4];
+	b.copy_from_slice(take(buf, 4)?);
+	Ok(u32::from_be_bytes(b))
+}
+
+impl Keystore {
+	pub fn new() -> Keystore {
+		Keystore::default()
+	}
+
+	// replaces any key already under name.
+	pub fn insert(&mut self, name: &str, key: Key) {
+		self.keys.insert(name.to_string(), key);
+	}
+
+	pub fn get(&self, name: &str) -> Option<&Key> {
+		self.keys.get(name)
+	}
+
+	pub fn remove(&mut self, name: &str) -> Option<Key> {
+		self.keys.remove(name)
+	}
+
+	pub fn names(&self) -> Vec<&str> {
+		self.keys.keys().map(|k| k.as_str()).collect()
+	}
+
+	// the iteration count is calibrated to take about SAVE_TIME here, and
+	// stored in the file. the file is written next to path and renamed over.
+	pub fn save<P: AsRef<Path>>(&self, path: P, passphrase: &[u8]) -> io::Result<()> {
+		let mut rng = SpritzRng::from_os()?;
+		let mut salt = [0; SALT_LEN];
+		rng.fill_bytes(&mut salt);
+		let out = self.seal(passphrase, &salt, calibrate(SAVE_TIME), &Nonce::generate(&mut rng));
+
+		let mut tmp = path.as_ref().to_path_buf().into_os_string();
+		tmp.push(".tmp");
+		{
+			let mut f = File::create(&tmp)?;
+			f.write_all(&out)?;
+			f.sync_all()?;
+		}
+		fs::rename(&tmp, path)
+	}
+
+	pub fn load<P: AsRef<Path>>(path: P, passphrase: &[u8]) -> io::Result<Keystore> {
+		let mut buf = vec![];
+		File::open(path)?.read_to_end(&mut buf)?;
+		Ok(Keystore::open(passphrase, &buf, MAX_ITERATIONS)?)
+	}
+
+	fn seal(&self, passphrase: &[u8], salt: &[u8; SALT_LEN], iterations: u32, nonce: &Nonce) -> Vec<u8> {
+		let mut body = vec![];
+		body.extend_from_slice(&(self.keys.len() as u32).to_be_bytes());
+		for (name, key) in &self.keys {
+			body.extend_from_slice(&(name.len() as u32).to_be_bytes());
+			body.extend_from_slice(name.as_bytes());
+			body.extend_from_slice(key.as_bytes());
+		}
+
+		let mut out = MAGIC.to_vec();
+		out.extend_from_slice(&iterations.to_be_bytes());
+		out.extend_from_slice(salt);
+		let aead = SpritzAead::new(&pbkdf(passphrase, salt, iterations));
+		let sealed = aead.seal(nonce, &out, &body);
+		zeroize(&mut body);
+		out.extend_from_slice(nonce.as_bytes());
+		out.extend_from_slice(&sealed);
+		out
+	}
+
+	// the header isn't authenticated until the pbkdf has run, so counts
+	// over max_iterations are refused first.
+	fn open(passphrase: &[u8], mut buf: &[u8], max_iterations: u32) -> Result<Keystore> {
+		let buf = &mut buf;
+		let header = take(buf, HEADER_LEN)?;
+		if &header[.. 8] != MAGIC {
+			return Err(Error::InvalidLength);
+		}
+		let mut h = &header[8 ..];
+		let iterations = take_u32(&mut h)?;
+		if iterations < MIN_ITERATIONS || iterations > max_iterations {
+			return Err(Error::OutOfDomain);
+		}
+		let mut n = [0; NONCE_LEN];
+		n.copy_from_slice(take(buf, NONCE_LEN)?);
+		let aead = SpritzAead::new(&pbkdf(passphrase, h, iterations));
+		let mut body = aead.open(&Nonce::new(n), header, buf)?;
+
+		let r = Keystore::parse(&body);
+		zeroize(&mut body);
+		r
+	}
+
+	fn parse(mut body: &[u8]) -> Result<Keystore> {
+		let body = &mut body;
+		let mut ks = Keystore::new();
+		for _ in 0 .. take_u32(body)? {
+			let n = take_u32(body)? as usize;
+			let name = String::from_utf8(take(body, n)?.to_vec()).map_err(|_| Error::InvalidLength)?;
+			let mut k = [0; KEY_LEN];
+			k.copy_from_slice(take(body, KEY_LEN)?);
+			ks.keys.insert(name, Key::new(k));
+		}
+		if !body.is_empty() {
+			return Err(Error::InvalidLength);
+		}
+		Ok(ks)
+	}
+}
+
+#[test]
+fn keystore() {
+	let mut ks = Keystore::new();
+	ks.insert("signing", Key::new([1; KEY_LEN]));
+	ks.insert("backup", Key::new([2; KEY_LEN]));
+	let (salt, nonce) = ([7; SALT_LEN], Nonce::from_counter(1));
+	let raw = ks.seal(b"passphrase", &salt, MIN_ITERATIONS, &nonce);
+
+	let max = 2 * MIN_ITERATIONS;
+	let back = Keystore::open(b"passphrase", &raw, max).unwrap();
+	assert_eq!(back.names(), ["backup", "signing"]);
+	assert_eq!(back.get("signing").unwrap().as_bytes(), &[1; KEY_LEN]);
+	assert!(back.get("missing").is_none());
+	assert_eq!(Keystore::open(b"wrong", &raw, max).err(), Some(Error::InvalidTag));
+
+	let mut bad = raw.clone();
+	bad[11] ^= 1;
+	assert_eq!(Keystore::open(b"passphrase", &bad, max).err(), Some(Error::InvalidTag));
+	assert_eq!(Keystore::open(b"passphrase", &raw[.. 20], max).err(), Some(Error::Truncated));
+	let mut slow = raw.clone();
+	slow[8 .. 12].copy_from_slice(&u32::MAX.to_be_bytes());
+	assert_eq!(Keystore::open(b"passphrase", &slow, max).err(), Some(Error::OutOfDomain));
+	assert_eq!(Keystore::open(b"passphrase", &slow, MAX_ITERATIONS).err(), Some(Error::OutOfDomain));
+
+	let path = ::std::env::temp_dir().join(format!("spritz-keystore-test-{}", ::std::process::id()));
+	Keystore::new().save(&path, b"pw").unwrap();
+	assert!(Keystore::load(&path, b"pw").unwrap().names().is_empty());
+	assert!(Keystore::load(&path, b"other").is_err());
+	fs::remove_file(&path).unwrap();
+}
@@ -3,16 +3,238 @@
 // https://people.csail.mit.edu/rivest/pubs/RS14.pdf
 // NOTE: N must be a power of two because you += 2 on w and you xor instead of add/sub.
 
+// S and I follow the names used in the paper.
+#![allow(non_snake_case)]
+// the cipher, hash, MAC, AEAD and duplex work without std. Vec-returning
+// functions need the alloc feature, and io adapters, files and threads
+// need std. std is on by default.
+//
+// without std nothing allocates, and the stack stays small enough for parts
+// with a few KB of RAM: a Spritz state is at most 320 bytes, the AEAD and
+// duplex copy it once per message and add at most a 64 byte block, and the
+// KDF holds one state and a 32 byte block. aead::Sealer and Opener handle
+// messages too big to buffer whole.
+//
+// the cipher, hash, mac, aead, rng and kdf features each bring in the
+// module of that name, all on by default; aead needs mac for the tag. the
+// permutation, duplex and keys are always there, and modules built on the
+// others, like stream and vault, come in when what they use does.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+#[macro_use]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate core;
+
+#[cfg(feature = "aead")]
+pub mod aead;
+#[cfg(feature = "analysis")]
+pub mod analysis;
+#[cfg(feature = "compat-arduino")]
+pub mod arduino;
+#[cfg(feature = "armor")]
+pub mod armor;
+#[cfg(all(feature = "std", feature = "aead"))]
+pub mod authlog;
+#[cfg(feature = "rng")]
+pub mod blinded;
+pub mod block;
+#[cfg(feature = "alloc")]
+pub mod channel;
+#[cfg(feature = "cipher")]
+pub mod cipher;
+#[cfg(feature = "rng")]
+pub mod commitment;
+#[cfg(feature = "hash")]
+pub mod consthash;
+pub mod duplex;
+#[cfg(all(feature = "std", feature = "aead", feature = "rng"))]
+pub mod dotenv;
+#[cfg(feature = "encoding")]
+pub mod encoding;
+pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod fpe;
+#[cfg(all(feature = "alloc", feature = "rng"))]
+pub mod handshake;
+#[cfg(feature = "hash")]
+pub mod hash;
+#[cfg(feature = "hooks")]
+pub mod hooks;
+#[cfg(feature = "kdf")]
+pub mod kdf;
+pub mod key;
+#[cfg(all(feature = "std", feature = "aead", feature = "rng", feature = "kdf"))]
+pub mod keystore;
+#[cfg(feature = "mlock")]
+pub mod locked;
+#[cfg(feature = "mac")]
+pub mod mac;
+#[cfg(all(feature = "std", feature = "hash"))]
+pub mod manifest;
+pub mod metrics;
+#[cfg(feature = "experimental")]
+pub mod multi;
+#[cfg(all(feature = "std", feature = "cipher"))]
+pub mod pipeline;
+#[cfg(all(feature = "std", feature = "aead"))]
+pub mod pool;
+#[cfg(all(feature = "alloc", feature = "hash"))]
+pub mod pow;
+pub mod prelude;
+#[cfg(feature = "std")]
+pub mod progress;
+pub mod protocol;
+pub mod reduced;
+#[cfg(all(feature = "hash", feature = "aead"))]
+pub mod selftest;
+pub mod sponge;
+#[cfg(feature = "rng")]
+pub mod rng;
+#[cfg(feature = "analysis")]
+pub mod stats;
+#[cfg(all(feature = "std", feature = "aead"))]
+pub mod stream;
+pub mod transcript;
+#[cfg(all(feature = "std", feature = "aead", feature = "rng"))]
+pub mod vault;
+#[cfg(feature = "encoding")]
+pub mod words;
+
+#[cfg(all(feature = "alloc", feature = "aead"))]
+pub use aead::seal_batch;
+#[cfg(all(feature = "std", feature = "aead"))]
+pub use aead::seal_batch_parallel;
+#[cfg(feature = "aead")]
+pub use aead::SpritzAead;
+#[cfg(all(feature = "std", feature = "aead"))]
+pub use authlog::AuthLog;
+pub use block::SpritzBlock;
+#[cfg(feature = "alloc")]
+pub use channel::{Role, SecureChannel};
+#[cfg(feature = "hash")]
+pub use consthash::{const_hash256, const_hash_array};
+#[cfg(feature = "cipher")]
+pub use cipher::{Encryptor, KeystreamExt};
+pub use duplex::SpritzDuplex;
+pub use error::{Error, Result};
+#[cfg(all(feature = "alloc", feature = "hash"))]
+pub use hash::hash_batch;
+#[cfg(all(feature = "std", feature = "hash"))]
+pub use hash::hash_batch_parallel;
+#[cfg(feature = "hash")]
+pub use hash::{hash_batch_into, Digest, SpritzHasher};
+#[cfg(feature = "kdf")]
+pub use kdf::pbkdf;
+#[cfg(feature = "mac")]
+pub use key::derive_nonce;
+pub use key::{Fingerprint, Key, Nonce};
+#[cfg(all(feature = "std", feature = "aead", feature = "rng", feature = "kdf"))]
+pub use keystore::Keystore;
+#[cfg(feature = "mlock")]
+pub use locked::Locked;
+#[cfg(feature = "mac")]
+pub use mac::{SpritzMac, Tag};
+#[cfg(all(feature = "std", feature = "hash"))]
+pub use manifest::{hash_dir, verify_dir, Manifest};
+pub use metrics::Metrics;
+#[cfg(feature = "std")]
+pub use progress::{Cancellable, OutputFile, ProgressReader, ProgressWriter};
+#[cfg(all(feature = "std", feature = "cipher"))]
+pub use pipeline::KeystreamPipeline;
+#[cfg(all(feature = "std", feature = "aead"))]
+pub use pool::SpritzPool;
+pub use protocol::Protocol;
+pub use reduced::SpritzN;
+#[cfg(feature = "rng")]
+pub use rng::SpritzRng;
+#[cfg(all(feature = "hash", feature = "aead"))]
+pub use selftest::{self_test, SelfTestError};
+pub use sponge::SpongePermutation;
+#[cfg(all(feature = "std", feature = "aead"))]
+pub use stream::{copy_decrypt, copy_encrypt, encrypt_parallel, open_chunks, seal_chunks, DecryptReader, EncryptWriter};
+#[cfg(all(feature = "std", feature = "aead", feature = "cipher"))]
+pub use stream::{KeystreamReader, XorReader, XorWriter};
+pub use transcript::Transcript;
+#[cfg(all(feature = "std", feature = "aead", feature = "rng"))]
+pub use vault::SpritzVault;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use core::fmt;
+use core::ptr;
+use core::sync::atomic;
+
 const N: usize = 256;
+// every index into S is a u8, so with exactly 256 entries the compiler can
+// prove them all in bounds and drops the checks (a release build of the
+// squeeze and shuffle loops has none). this keeps it that way without unsafe.
+const _: () = assert!(N == u8::MAX as usize + 1);
+// N / 2 and N - 1 as register values, worked out once here rather than
+// cast at each use, where a u8 cast of a usize would hide a mistake.
+const HALF: u8 = (N / 2) as u8;
+const LAST: u8 = (N - 1) as u8;
+const RATCHET_LEN: usize = 32;
+const KEYSTREAM_BLOCK: usize = 64;
+
+// hands e to the state's hook with the hooks feature, and is nothing
+// without it.
+macro_rules! hook {
+	($sp:expr, $e:expr) => {
+		#[cfg(feature = "hooks")]
+		$sp.emit($e);
+	};
+}
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Phase {
+	Absorbing,
+	Squeezing,
+}
+
+// running totals since the state was created.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Usage {
+	pub absorbed: u64,
+	pub squeezed: u64,
+	pub shuffles: u64,
+}
+
+// S starts on a cache line and fills exactly four; the registers share the
+// line after it, in the order update() and output() use them, so the hot
+// state is five lines whatever the surrounding allocation. 16-bit parts
+// have no cache, and there the alignment would only cost RAM in padding.
+#[derive(Clone)]
+#[cfg_attr(not(target_pointer_width = "16"), repr(C, align(64)))]
+#[cfg_attr(target_pointer_width = "16", repr(C))]
 pub struct Spritz {
-	S: [u8; 256],
+	S: [u8; N],
 	i: u8,
+	w: u8,
 	j: u8,
 	k: u8,
 	z: u8,
 	a: u8,
-	w: u8,
+	phase: Phase,
+	usage: Usage,
+	#[cfg(feature = "hooks")]
+	hook: Option<hooks::Hook>,
+}
+
+// only what's safe to log: the permutation and registers are the key.
+impl fmt::Debug for Spritz {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Spritz").field("phase", &self.phase).field("usage", &self.usage).finish_non_exhaustive()
+	}
+}
+
+impl fmt::Display for Usage {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} bytes absorbed, {} squeezed, {} shuffles", self.absorbed, self.squeezed, self.shuffles)
+	}
 }
 
 impl Spritz {
@@ -22,41 +244,147 @@ impl Spritz {
 		sp
 	}
 
-	pub fn xor_key_stream(&mut self, dst: &mut [u8], src: &[u8]) {
-		assert!(dst.len() == src.len());
-		for (i, v) in src.iter().enumerate() {
-			dst[i] = v ^ self.drip();
+	// key setup followed by an iv, as in section 4 of the paper.
+	pub fn new_with_iv(key: &[u8], iv: &[u8]) -> Spritz {
+		let mut sp = Spritz::new(key);
+		sp.absorb_stop();
+		sp.absorb(iv);
+		sp
+	}
+
+	// encrypt or decrypt buf in place. outside the crate this is only
+	// reachable through cipher::Encryptor, which can't be had without a
+	// nonce. the keystream is made a block at a time and xored in a word
+	// at a time.
+	pub(crate) fn apply_keystream(&mut self, buf: &mut [u8]) {
+		let mut ks = [0; KEYSTREAM_BLOCK];
+		for chunk in buf.chunks_mut(KEYSTREAM_BLOCK) {
+			let ks = &mut ks[.. chunk.len()];
+			self.squeeze_into(ks);
+			xor_words(chunk, ks);
 		}
+		wipe_temp(&mut ks);
 	}
 
-	pub fn hash256(msg: &[u8]) -> Vec<u8> {
-		let mut sp = Spritz::initialize_state();
-		sp.absorb(msg);
-		sp.absorb_stop();
-		sp.absorb(&[32]);
-		return sp.squeeze(32);
+	// one continuous keystream across all of bufs, in order: the same as
+	// applying it to their concatenation.
+	#[cfg(any(test, feature = "cipher"))]
+	pub(crate) fn apply_keystream_vectored(&mut self, bufs: &mut [&mut [u8]]) {
+		for b in bufs.iter_mut() {
+			self.apply_keystream(b);
+		}
+	}
+
+	// dst = src xor keystream, with no nonce. kept from before the
+	// Encryptor typestate so existing callers still build; new code gets
+	// the same method from Spritz::with_key(key).nonce(nonce).
+	#[deprecated(note = "no nonce is asked for; use Spritz::with_key(key).nonce(nonce).xor_key_stream")]
+	pub fn xor_key_stream(&mut self, dst: &mut [u8], src: &[u8]) -> Result<()> {
+		if dst.len() != src.len() {
+			return Err(Error::UnexpectedLength { expected: src.len(), actual: dst.len() });
+		}
+		dst.copy_from_slice(src);
+		self.apply_keystream(dst);
+		Ok(())
+	}
+
+	// rebuild the state from a squeezed key alone. every spritz step is
+	// invertible, so otherwise whoever captures the state can run it
+	// backwards and recover the keystream that was already used.
+	pub fn ratchet(&mut self) {
+		let mut k = [0; RATCHET_LEN];
+		self.squeeze_into(&mut k);
+		let usage = self.usage;
+		#[cfg(feature = "hooks")]
+		let hook = self.hook;
+		*self = Spritz::initialize_state();
+		self.usage = usage;
+		#[cfg(feature = "hooks")]
+		{
+			self.hook = hook;
+		}
+		self.absorb(&k);
+		wipe_temp(&mut k);
+	}
+
+	// squeezing once output has been taken since the last absorb.
+	pub fn phase(&self) -> Phase {
+		self.phase
+	}
+
+	pub fn usage(&self) -> Usage {
+		self.usage
 	}
 
-	fn initialize_state() -> Spritz {
+	// overwrite S and the registers with zeros now, whether or not the
+	// zeroize feature is on. the state is useless afterwards; its output
+	// is all zeros.
+	pub fn wipe(&mut self) {
+		zeroize(&mut self.S);
+		let regs = [&mut self.i, &mut self.j, &mut self.k, &mut self.z, &mut self.a, &mut self.w];
+		for r in regs {
+			zeroize(::core::slice::from_mut(r));
+		}
+	}
+
+	pub fn finish_zeroizing(mut self) {
+		self.wipe();
+	}
+
+	pub(crate) const fn initialize_state() -> Spritz {
 		let mut sp = Spritz {
-    		S: [0; 256],
+			S: [0; N],
 			i: 0,
 			j: 0,
 			k: 0,
 			z: 0,
 			a: 0,
 			w: 1,
+			phase: Phase::Absorbing,
+			usage: Usage { absorbed: 0, squeezed: 0, shuffles: 0 },
+			#[cfg(feature = "hooks")]
+			hook: None,
 		};
-		for (i, v) in sp.S.iter_mut().enumerate() {
-			*v = i as u8;
-		};
+		let mut v = 0;
+		while v < N {
+			sp.S[v] = v as u8;
+			v += 1;
+		}
 		sp
 	}
 
-	fn absorb(&mut self, I: &[u8]) {
-		for b in I {
-			self.absorb_byte(*b);
+	// absorb_byte for each byte, but with the shuffle check made once per
+	// run of bytes that fits before the next shuffle instead of per nibble.
+	pub(crate) fn absorb(&mut self, I: &[u8]) {
+		let mut rest = I;
+		if self.hooked() {
+			I.iter().for_each(|b| self.absorb_byte(*b));
+			rest = &[];
+		}
+		while !rest.is_empty() {
+			if self.a == HALF {
+				self.shuffle();
+			}
+			let room = (HALF - self.a) as usize / 2;
+			if room == 0 {
+				// one nibble left before the shuffle, after an odd number of stops.
+				self.absorb_byte(rest[0]);
+				rest = &rest[1 ..];
+				continue;
+			}
+			let (run, tail) = rest.split_at(room.min(rest.len()));
+			let mut a = self.a;
+			for b in run {
+				self.swap_nibble(a, b & 0xf);
+				self.swap_nibble(a.wrapping_add(1), b >> 4);
+				a = a.wrapping_add(2);
+			}
+			self.a = a;
+			rest = tail;
 		}
+		self.usage.absorbed += I.len() as u64;
+		self.phase = Phase::Absorbing;
+		self.check_invariants();
 	}
 
 	fn absorb_byte(&mut self, b: u8) {
@@ -65,55 +393,175 @@ impl Spritz {
 	}
 
 	fn absorb_nibble(&mut self, x: u8) {
-		if self.a == (N / 2) as u8 {
+		if self.a == HALF {
 			self.shuffle();
 		}
-		self.S.swap(self.a as usize, (x.wrapping_add((N / 2) as u8)) as usize);
+		self.swap_nibble(self.a, x);
+		hook!(self, hooks::Event::AbsorbNibble { a: self.a, x });
 		self.a = self.a.wrapping_add(1);
 	}
 
-	fn absorb_stop(&mut self) {
-		if self.a == (N / 2) as u8 {
+	#[cfg(feature = "hooks")]
+	fn hooked(&self) -> bool {
+		self.hook.is_some()
+	}
+
+	#[cfg(not(feature = "hooks"))]
+	fn hooked(&self) -> bool {
+		false
+	}
+
+	// S[a] <-> S[N/2 + x]. x is secret and a isn't, so with the
+	// hardened-absorb feature all 16 entries x could pick are read and
+	// written, and which one changed can't be seen in the access pattern.
+	const fn swap_nibble(&mut self, a: u8, x: u8) {
+		if cfg!(feature = "hardened-absorb") {
+			self.swap_nibble_scan(a, x);
+		} else {
+			self.S.swap(a as usize, x.wrapping_add(HALF) as usize);
+		}
+	}
+
+	// a is always below N/2 here, so it's never one of the scanned entries.
+	const fn swap_nibble_scan(&mut self, a: u8, x: u8) {
+		let sa = self.S[a as usize];
+		let mut t = 0;
+		let mut c = 0;
+		while c < 16 {
+			let v = &mut self.S[HALF as usize + c as usize];
+			// all ones when c == x.
+			let m = (((c ^ x) as u16).wrapping_sub(1) >> 8) as u8;
+			t |= *v & m;
+			*v = (*v & !m) | (sa & m);
+			c += 1;
+		}
+		self.S[a as usize] = t;
+	}
+
+	pub(crate) fn absorb_stop(&mut self) {
+		if self.a == HALF {
 			self.shuffle();
 		}
 		self.a = self.a.wrapping_add(1);
+		self.phase = Phase::Absorbing;
 	}
 
 	fn shuffle(&mut self) {
+		hook!(self, hooks::Event::Shuffle);
 		self.whip(2 * N);
 		self.crush();
 		self.whip(2 * N);
 		self.crush();
 		self.whip(2 * N);
 		self.a = 0;
+		self.usage.shuffles += 1;
+		self.check_invariants();
+	}
+
+	// with the paranoid feature, debug builds check that S is still a
+	// permutation, w is odd (so coprime to N) and a hasn't passed N/2.
+	fn check_invariants(&self) {
+		if !cfg!(all(feature = "paranoid", debug_assertions)) {
+			return;
+		}
+		let mut seen = [false; N];
+		for v in self.S.iter() {
+			assert!(!seen[*v as usize], "spritz: S is not a permutation");
+			seen[*v as usize] = true;
+		}
+		assert!(self.w & 1 == 1, "spritz: w is even");
+		assert!(self.a <= HALF, "spritz: a is past N/2");
 	}
 
 	fn whip(&mut self, r: usize) {
 		for _ in 0 .. r {
 			self.update();
 		}
+		hook!(self, hooks::Event::Whip { r, w: self.w });
 		// we can do this since N is a power of two.
 		self.w = self.w.wrapping_add(2);
 	}
 
+	// sorts each pair S[v], S[N - 1 - v] without branching on them, since
+	// they're secret: m is all ones exactly when y - x borrows.
 	fn crush(&mut self) {
-		for v in 0 .. (N / 2) as u8 {
-			let idx = ((N - 1) as u8).wrapping_sub(v);
-			if self.S[v as usize] > self.S[idx as usize] {
-				self.S.swap(v as usize, idx as usize);
-			}
+		self.crush_pairs();
+		hook!(self, hooks::Event::Crush);
+	}
+
+	const fn crush_pairs(&mut self) {
+		let mut v = 0;
+		while v < HALF {
+			let idx = LAST.wrapping_sub(v);
+			let (x, y) = (self.S[v as usize], self.S[idx as usize]);
+			let m = ((y as u16).wrapping_sub(x as u16) >> 8) as u8;
+			let d = (x ^ y) & m;
+			self.S[v as usize] = x ^ d;
+			self.S[idx as usize] = y ^ d;
+			v += 1;
+		}
+	}
+
+	// the sponge's raw output: squeeze, squeeze_array, squeeze_into and
+	// drip. these are the unguarded way round the nonce typestate in
+	// cipher.rs; nothing here asks for a nonce, so a state keyed the same
+	// way gives the same bytes every time. they are for building hashes,
+	// MACs and other constructions on. to encrypt, use an Encryptor.
+	#[cfg(feature = "alloc")]
+	pub fn squeeze(&mut self, r: usize) -> Vec<u8> {
+		let mut p = vec![0; r];
+		self.squeeze_into(&mut p);
+		p
+	}
+
+	pub fn squeeze_array<const R: usize>(&mut self) -> [u8; R] {
+		let mut out = [0; R];
+		self.squeeze_into(&mut out);
+		out
+	}
+
+	// drip() into every byte of out.
+	pub fn squeeze_into(&mut self, out: &mut [u8]) {
+		if self.hooked() {
+			out.iter_mut().for_each(|v| *v = self.drip());
+			return;
 		}
+		self.begin_squeeze(out.len());
+		self.update_output(out);
 	}
 
-	fn squeeze(&mut self, r: usize) -> Vec<u8> {
+	// update() and output() for each byte of out, with the registers kept in
+	// locals and written back once at the end.
+	fn update_output(&mut self, out: &mut [u8]) {
+		let (mut i, mut j, mut k, mut z, w) = (self.i, self.j, self.k, self.z, self.w);
+		let S = &mut self.S;
+		for v in out.iter_mut() {
+			i = i.wrapping_add(w);
+			j = k.wrapping_add(S[j.wrapping_add(S[i as usize]) as usize]);
+			k = i.wrapping_add(k).wrapping_add(S[j as usize]);
+			S.swap(i as usize, j as usize);
+			let t0 = S[z.wrapping_add(k) as usize];
+			let t1 = S[i.wrapping_add(t0) as usize];
+			z = S[j.wrapping_add(t1) as usize];
+			*v = z;
+		}
+		self.i = i;
+		self.j = j;
+		self.k = k;
+		self.z = z;
+	}
+
+	// what drip() does before each byte, done once for n of them: after the
+	// shuffle a is 0, so the check can't fire again until the next absorb.
+	fn begin_squeeze(&mut self, n: usize) {
+		if n == 0 {
+			return;
+		}
 		if self.a > 0 {
 			self.shuffle();
 		}
-		let mut p = Vec::with_capacity(r);
-		for _ in 0 .. r {
-			p.push(self.drip())
-		}
-		return p;
+		self.usage.squeezed += n as u64;
+		self.phase = Phase::Squeezing;
 	}
 
 	pub fn drip(&mut self) -> u8 {
@@ -121,17 +569,21 @@ impl Spritz {
 			self.shuffle();
 		}
 		self.update();
-		return self.output();
+		self.usage.squeezed += 1;
+		self.phase = Phase::Squeezing;
+		let z = self.output();
+		hook!(self, hooks::Event::Drip { i: self.i, j: self.j, k: self.k, z });
+		z
 	}
 
-	fn output(&mut self) -> u8 {
+	const fn output(&mut self) -> u8 {
 		let t0 = self.S[(self.z.wrapping_add(self.k)) as usize];
 		let t1 = self.S[(self.i.wrapping_add(t0)) as usize];
 		self.z = self.S[(self.j.wrapping_add(t1)) as usize];
-		return self.z;
+		self.z
 	}
 
-	fn update(&mut self) {
+	const fn update(&mut self) {
 		self.i = self.i.wrapping_add(self.w);
 		let idx = self.j.wrapping_add(self.S[self.i as usize]);
 		self.j = self.k.wrapping_add(self.S[idx as usize]);
@@ -141,7 +593,67 @@ impl Spritz {
 
 }
 
+#[cfg(feature = "zeroize")]
+impl Drop for Spritz {
+	fn drop(&mut self) {
+		self.wipe();
+	}
+}
+
+// overwrite with zeros in a way the optimizer can't drop as a dead store.
+pub(crate) fn zeroize(buf: &mut [u8]) {
+	for v in buf.iter_mut() {
+		unsafe { ptr::write_volatile(v, 0) };
+	}
+	atomic::compiler_fence(atomic::Ordering::SeqCst);
+}
+
+// for secret temporaries; only wiped with the zeroize feature.
+pub(crate) fn wipe_temp(buf: &mut [u8]) {
+	if cfg!(feature = "zeroize") {
+		zeroize(buf);
+	}
+}
+
+// copy a slice into an array, checking the length.
+pub(crate) fn fixed<const L: usize>(v: &[u8]) -> Result<[u8; L]> {
+	if v.len() != L {
+		return Err(Error::UnexpectedLength { expected: L, actual: v.len() });
+	}
+	let mut a = [0; L];
+	a.copy_from_slice(v);
+	Ok(a)
+}
+
+// dst ^= src, a machine word at a time.
+fn xor_words(dst: &mut [u8], src: &[u8]) {
+	const W: usize = core::mem::size_of::<usize>();
+	let mut d = dst.chunks_exact_mut(W);
+	let mut s = src.chunks_exact(W);
+	for (a, b) in (&mut d).zip(&mut s) {
+		let x = usize::from_ne_bytes(a[..].try_into().unwrap()) ^ usize::from_ne_bytes(b.try_into().unwrap());
+		a.copy_from_slice(&x.to_ne_bytes());
+	}
+	for (a, b) in d.into_remainder().iter_mut().zip(s.remainder()) {
+		*a ^= b;
+	}
+}
+
+// compare without an early exit, for tags and digests.
+pub(crate) fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+	if a.len() != b.len() {
+		return false;
+	}
+	let mut d = 0;
+	for (x, y) in a.iter().zip(b.iter()) {
+		d |= x ^ y;
+	}
+	d == 0
+}
+
+#[cfg(all(feature = "alloc", feature = "hash"))]
 #[test]
+#[allow(clippy::useless_vec)]
 fn it_works() {
 	let mut sp = Spritz::new(b"ABC");
 	let v = vec![0x77, 0x9a, 0x8e, 0x01, 0xf9, 0xe9, 0xcb, 0xc0];
@@ -181,3 +693,165 @@ fn it_works() {
 	let v = vec![0xff, 0x8c, 0xf2, 0x68, 0x09, 0x4c, 0x87, 0xb9];
 	assert_eq!(&h[..8], &v[..]);
 }
+
+#[cfg(all(feature = "alloc", feature = "hash", feature = "mac"))]
+#[test]
+fn arrays() {
+	let h: [u8; 32] = Spritz::hash_array(b"ABC");
+	assert_eq!(&h[..], &Spritz::hash256(b"ABC")[..]);
+	let mut t = [0; 255];
+	Spritz::mac_into(b"key", b"msg", &mut t).unwrap();
+	assert_eq!(Spritz::mac_array::<255>(b"key", b"msg")[..], t[..]);
+	let mut a = Spritz::new(b"key");
+	let mut b = a.clone();
+	assert_eq!(a.squeeze_array::<300>()[..], b.squeeze(300)[..]);
+}
+
+#[test]
+fn ratchet() {
+	let mut a = Spritz::new(b"key");
+	let mut b = Spritz::new(b"key");
+	let mut c = Spritz::new(b"key");
+	let mut x = [0; 16];
+	let mut y = [0; 16];
+	let mut z = [0; 16];
+	a.ratchet();
+	b.ratchet();
+	a.apply_keystream(&mut x);
+	b.apply_keystream(&mut y);
+	c.apply_keystream(&mut z);
+	assert_eq!(x, y);
+	assert!(x != z);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn usage() {
+	let mut sp = Spritz::new(b"key");
+	assert_eq!(sp.phase(), Phase::Absorbing);
+	let mut out = [0; 10];
+	sp.apply_keystream(&mut out);
+	assert_eq!(sp.phase(), Phase::Squeezing);
+	assert_eq!(sp.usage(), Usage { absorbed: 3, squeezed: 10, shuffles: 1 });
+	sp.absorb_stop();
+	assert_eq!(sp.phase(), Phase::Absorbing);
+	assert_eq!(
+		format!("{:?}", sp),
+		"Spritz { phase: Absorbing, usage: Usage { absorbed: 3, squeezed: 10, shuffles: 1 }, .. }"
+	);
+	assert_eq!(sp.usage().to_string(), "3 bytes absorbed, 10 squeezed, 1 shuffles");
+}
+
+#[test]
+fn swap_nibble_scan() {
+	let mut a = Spritz::new(b"key");
+	let mut b = a.clone();
+	for x in 0 .. 16 {
+		a.S.swap(x as usize * 3, x as usize + HALF as usize);
+		b.swap_nibble_scan(x * 3, x);
+		assert!(a.S[..] == b.S[..]);
+	}
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn invariants() {
+	let mut sp = Spritz::new(b"key");
+	sp.check_invariants();
+	sp.S[0] = sp.S[1];
+	let caught = ::std::panic::catch_unwind(move || sp.check_invariants()).is_err();
+	assert_eq!(caught, cfg!(all(feature = "paranoid", debug_assertions)));
+}
+
+#[test]
+fn wipe() {
+	let mut sp = Spritz::new(b"key");
+	sp.wipe();
+	assert!(sp.S.iter().chain(&[sp.i, sp.j, sp.k, sp.z, sp.a, sp.w]).all(|v| *v == 0));
+	Spritz::new(b"key").finish_zeroizing();
+}
+
+#[allow(deprecated)]
+#[test]
+fn apply_keystream() {
+	let mut a = Spritz::new_with_iv(b"key", b"iv");
+	let mut b = Spritz::new_with_iv(b"key", b"iv");
+	let mut buf = *b"some plaintext";
+	let mut out = [0; 14];
+	a.apply_keystream(&mut buf);
+	b.xor_key_stream(&mut out, b"some plaintext").unwrap();
+	assert_eq!(buf, out);
+	let mut c = Spritz::new_with_iv(b"key", b"other");
+	c.apply_keystream(&mut out);
+	assert!(&out != b"some plaintext");
+}
+
+#[test]
+fn vectored() {
+	let mut a = Spritz::new(b"key");
+	let mut b = Spritz::new(b"key");
+	let mut whole = [0x33; 150];
+	a.apply_keystream(&mut whole);
+	let (mut x, mut y, mut z) = ([0x33; 5], [0x33; 0], [0x33; 145]);
+	b.apply_keystream_vectored(&mut [&mut x, &mut y, &mut z]);
+	assert_eq!(whole[.. 5], x);
+	assert_eq!(whole[5 ..], z[..]);
+}
+
+#[test]
+fn keystream_blocks() {
+	// the same keystream whatever the buffer lengths.
+	let mut a = Spritz::new(b"key");
+	let mut b = Spritz::new(b"key");
+	let mut x = [0x5a; 203];
+	let mut y = x;
+	a.apply_keystream(&mut x);
+	for part in y.chunks_mut(13) {
+		for v in part.iter_mut() {
+			*v ^= b.drip();
+		}
+	}
+	assert_eq!(x[..], y[..]);
+}
+
+#[cfg(all(feature = "hash", feature = "mac"))]
+#[test]
+fn into() {
+	let mut d = [0; 32];
+	Spritz::hash_into(b"ABC", &mut d).unwrap();
+	assert_eq!(&d[..], &Spritz::hash256(b"ABC")[..]);
+	let mut short = [0; 8];
+	Spritz::hash_into(b"ABC", &mut short).unwrap();
+	assert!(short[..] != d[.. 8]);
+	assert_eq!(Spritz::hash_into(b"ABC", &mut [0; 256]), Err(Error::InvalidLength));
+
+	let mut t = [0; 32];
+	Spritz::mac_into(b"key", b"msg", &mut t).unwrap();
+	assert_eq!(Spritz::mac_into(b"key", b"msg", &mut [0; 300]), Err(Error::InvalidLength));
+	let mut m = SpritzMac::new(b"key");
+	m.update(b"msg");
+	assert_eq!(&t[..], m.finalize().as_bytes());
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn absorb_runs() {
+	// against absorbing nibble by nibble, across shuffles and odd positions.
+	let data: Vec<u8> = (0 .. 700).map(|i| (i * 7) as u8).collect();
+	for stops in 0 .. 3 {
+		for len in [0, 1, 63, 64, 65, 127, 700] {
+			let mut a = Spritz::initialize_state();
+			let mut b = Spritz::initialize_state();
+			for _ in 0 .. stops {
+				a.absorb_stop();
+				b.absorb_stop();
+			}
+			a.absorb(&data[.. len]);
+			for v in &data[.. len] {
+				b.absorb_byte(*v);
+			}
+			assert_eq!(a.S[..], b.S[..]);
+			assert_eq!((a.a, a.usage.shuffles), (b.a, b.usage.shuffles));
+		}
+	}
+}
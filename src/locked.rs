@@ -0,0 +1,157 @@
+// page-locked memory for keys and cipher state, with mlock on unix and
+// VirtualLock on windows, so they're never written out to swap.
+//
+// a Locked<T> keeps its value in an allocation of its own, page aligned
+// and a whole number of pages long, so unlocking it can't unlock a
+// neighbour. the allocation is zeroed before it's freed. locking fails past
+// RLIMIT_MEMLOCK and on platforms without it; the value is then kept in
+// ordinary memory, and is_locked says so.
+
+use std::alloc::{self, Layout};
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::ptr;
+use std::slice;
+
+use zeroize;
+
+pub struct Locked<T> {
+	p: *mut T,
+	layout: Layout,
+	locked: bool,
+}
+
+// Locked<T> owns its T like a Box does.
+unsafe impl<T: Send> Send for Locked<T> {}
+unsafe impl<T: Sync> Sync for Locked<T> {}
+
+impl<T> Locked<T> {
+	// v is moved in, so a copy of it may be left behind on the stack;
+	// build secrets in place with DerefMut where that matters.
+	pub fn new(v: T) -> Locked<T> {
+		let page = sys::page_size();
+		let size = mem::size_of::<T>().max(1).next_multiple_of(page);
+		let layout = Layout::from_size_align(size, page.max(mem::align_of::<T>())).unwrap();
+		let p = unsafe { alloc::alloc_zeroed(layout) } as *mut T;
+		if p.is_null() {
+			alloc::handle_alloc_error(layout);
+		}
+		let locked = unsafe { sys::lock(p as *const u8, size) };
+		unsafe { ptr::write(p, v) };
+		Locked { p, layout, locked }
+	}
+
+	pub fn is_locked(&self) -> bool {
+		self.locked
+	}
+}
+
+impl<T> Deref for Locked<T> {
+	type Target = T;
+
+	fn deref(&self) -> &T {
+		unsafe { &*self.p }
+	}
+}
+
+impl<T> DerefMut for Locked<T> {
+	fn deref_mut(&mut self) -> &mut T {
+		unsafe { &mut *self.p }
+	}
+}
+
+impl<T> Drop for Locked<T> {
+	fn drop(&mut self) {
+		unsafe {
+			ptr::drop_in_place(self.p);
+			zeroize(slice::from_raw_parts_mut(self.p as *mut u8, self.layout.size()));
+			if self.locked {
+				sys::unlock(self.p as *const u8, self.layout.size());
+			}
+			alloc::dealloc(self.p as *mut u8, self.layout);
+		}
+	}
+}
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "macos", target_os = "ios"))]
+mod sys {
+	use std::os::raw::{c_int, c_long, c_void};
+
+	extern "C" {
+		fn mlock(addr: *const c_void, len: usize) -> c_int;
+		fn munlock(addr: *const c_void, len: usize) -> c_int;
+		fn sysconf(name: c_int) -> c_long;
+	}
+
+	#[cfg(any(target_os = "linux", target_os = "android"))]
+	const SC_PAGESIZE: c_int = 30;
+	#[cfg(any(target_os = "macos", target_os = "ios"))]
+	const SC_PAGESIZE: c_int = 29;
+
+	pub fn page_size() -> usize {
+		match unsafe { sysconf(SC_PAGESIZE) } {
+			n if n > 0 => n as usize,
+			_ => 4096,
+		}
+	}
+
+	pub unsafe fn lock(p: *const u8, len: usize) -> bool {
+		mlock(p as *const c_void, len) == 0
+	}
+
+	pub unsafe fn unlock(p: *const u8, len: usize) {
+		munlock(p as *const c_void, len);
+	}
+}
+
+#[cfg(windows)]
+mod sys {
+	use std::os::raw::{c_int, c_void};
+
+	#[link(name = "kernel32")]
+	extern "system" {
+		fn VirtualLock(addr: *mut c_void, len: usize) -> c_int;
+		fn VirtualUnlock(addr: *mut c_void, len: usize) -> c_int;
+	}
+
+	// the same on every windows target rust supports.
+	pub fn page_size() -> usize {
+		4096
+	}
+
+	pub unsafe fn lock(p: *const u8, len: usize) -> bool {
+		VirtualLock(p as *mut c_void, len) != 0
+	}
+
+	pub unsafe fn unlock(p: *const u8, len: usize) {
+		VirtualUnlock(p as *mut c_void, len);
+	}
+}
+
+#[cfg(not(any(windows, target_os = "linux", target_os = "android", target_os = "macos", target_os = "ios")))]
+mod sys {
+	pub fn page_size() -> usize {
+		4096
+	}
+
+	pub unsafe fn lock(_: *const u8, _: usize) -> bool {
+		false
+	}
+
+	pub unsafe fn unlock(_: *const u8, _: usize) {}
+}
+
+#[test]
+fn locked() {
+	use key::Key;
+	use Spritz;
+
+	let k = Locked::new(Key::new([9; 32]));
+	assert_eq!(k.as_bytes(), &[9; 32][..]);
+	assert_eq!(k.p as usize % sys::page_size(), 0);
+
+	let mut sp = Locked::new(Spritz::new(k.as_bytes()));
+	let mut plain = Spritz::new(k.as_bytes());
+	assert_eq!(sp.squeeze(16), plain.squeeze(16));
+	let _ = sp.is_locked();
+}
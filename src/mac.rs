@@ -0,0 +1,169 @@
+// message authentication, the MAC from section 4 of the paper with r = 32.
+
+use core::convert::TryFrom;
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+
+use error::{Error, Result};
+use sponge::SpongePermutation;
+use {ct_eq, fixed, Spritz};
+
+pub const TAG_LEN: usize = 32;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Tag([u8; TAG_LEN]);
+
+impl Tag {
+	// == is constant time as well; this is just explicit about it.
+	pub fn ct_eq(&self, other: &Tag) -> bool {
+		ct_eq(&self.0, &other.0)
+	}
+
+	pub fn new(bytes: [u8; TAG_LEN]) -> Tag {
+		Tag(bytes)
+	}
+
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.0
+	}
+}
+
+impl PartialEq for Tag {
+	fn eq(&self, other: &Tag) -> bool {
+		self.ct_eq(other)
+	}
+}
+
+impl Eq for Tag {}
+
+impl TryFrom<&[u8]> for Tag {
+	type Error = Error;
+
+	fn try_from(v: &[u8]) -> Result<Tag> {
+		fixed(v).map(Tag)
+	}
+}
+
+impl From<[u8; TAG_LEN]> for Tag {
+	fn from(bytes: [u8; TAG_LEN]) -> Tag {
+		Tag::new(bytes)
+	}
+}
+
+// the one-shot MAC from the paper.
+impl Spritz {
+	// r = out.len(), absorbed as one byte and so under 256.
+	pub fn mac_into(key: &[u8], msg: &[u8], out: &mut [u8]) -> Result<()> {
+		if out.len() > u8::MAX as usize {
+			return Err(Error::InvalidLength);
+		}
+		Spritz::mac_to(key, msg, out);
+		Ok(())
+	}
+
+	// mac_into for callers that have already checked out.len().
+	pub(crate) fn mac_to(key: &[u8], msg: &[u8], out: &mut [u8]) {
+		let mut sp = Spritz::initialize_state();
+		sp.absorb(key);
+		sp.absorb_stop();
+		sp.absorb(msg);
+		sp.absorb_stop();
+		sp.absorb(&[out.len() as u8]);
+		sp.squeeze_into(out);
+	}
+
+	pub fn mac_array<const R: usize>(key: &[u8], msg: &[u8]) -> [u8; R] {
+		const { assert!(R <= u8::MAX as usize, "spritz: mac output over 255 bytes") };
+		let mut out = [0; R];
+		Spritz::mac_to(key, msg, &mut out);
+		out
+	}
+}
+
+#[derive(Clone, Debug)]
+pub struct SpritzMac<P = Spritz> {
+	sp: P,
+}
+
+impl SpritzMac {
+	pub fn new(key: &[u8]) -> SpritzMac {
+		SpritzMac::with_permutation(key)
+	}
+}
+
+impl<P: SpongePermutation> SpritzMac<P> {
+	pub fn with_permutation(key: &[u8]) -> SpritzMac<P> {
+		let mut sp = P::initialize();
+		sp.absorb(key);
+		sp.absorb_stop();
+		SpritzMac { sp }
+	}
+
+	pub fn update(&mut self, data: &[u8]) {
+		self.sp.absorb(data);
+	}
+
+	pub fn finalize(mut self) -> Tag {
+		self.sp.absorb_stop();
+		self.sp.absorb(&[TAG_LEN as u8]);
+		let mut t = [0; TAG_LEN];
+		self.sp.squeeze(&mut t);
+		Tag::new(t)
+	}
+
+	// same as Spritz::mac_array, for tags other than TAG_LEN bytes.
+	pub fn finalize_array<const R: usize>(mut self) -> [u8; R] {
+		const { assert!(R <= u8::MAX as usize, "spritz: mac output over 255 bytes") };
+		self.sp.absorb_stop();
+		self.sp.absorb(&[R as u8]);
+		let mut t = [0; R];
+		self.sp.squeeze(&mut t);
+		t
+	}
+
+	// constant time.
+	pub fn verify(self, tag: &Tag) -> Result<()> {
+		if self.finalize().ct_eq(tag) {
+			Ok(())
+		} else {
+			Err(Error::InvalidTag)
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl<P: SpongePermutation> Write for SpritzMac<P> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.update(buf);
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn mac() {
+	let mut m = SpritzMac::new(b"key");
+	m.update(b"hello ");
+	m.update(b"world");
+	let t = m.clone().finalize();
+	assert_eq!(m.clone().verify(&t), Ok(()));
+
+	let mut sp = Spritz::new(b"key");
+	sp.absorb_stop();
+	sp.absorb(b"hello world");
+	sp.absorb_stop();
+	sp.absorb(&[32]);
+	assert_eq!(t.as_bytes(), &sp.squeeze(32)[..]);
+
+	assert_eq!(m.clone().finalize_array::<16>(), Spritz::mac_array::<16>(b"key", b"hello world"));
+
+	m.update(b"!");
+	assert_eq!(m.verify(&t), Err(Error::InvalidTag));
+	let mut o = SpritzMac::new(b"other");
+	o.update(b"hello world");
+	assert_eq!(o.verify(&t), Err(Error::InvalidTag));
+}
@@ -0,0 +1,172 @@
+// digests of every file under a directory, for checking release artifacts.
+//
+// the text form is one line per file, sorted by path:
+//
+//   <64 hex digits>  <path>
+//
+// paths are relative, with / between components whatever the platform.
+// only regular files are listed; symlinks and other special files are
+// skipped, and symlinked directories aren't followed. the text is what
+// gets signed or MACed, and digest() is its hash.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use error::Error;
+use hash::{Digest, SpritzHasher, DIGEST_LEN};
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Manifest {
+	files: BTreeMap<String, Digest>,
+}
+
+// what verify_dir found different, by path.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Difference {
+	Missing(String),
+	Added(String),
+	Changed(String),
+}
+
+impl Manifest {
+	pub fn get(&self, path: &str) -> Option<&Digest> {
+		self.files.get(path)
+	}
+
+	// in path order.
+	pub fn files(&self) -> impl Iterator<Item = (&str, &Digest)> {
+		self.files.iter().map(|(p, d)| (p.as_str(), d))
+	}
+
+	pub fn len(&self) -> usize {
+		self.files.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.files.is_empty()
+	}
+
+	// the hash of the text form.
+	pub fn digest(&self) -> Digest {
+		let mut h = SpritzHasher::new();
+		h.update(self.to_string().as_bytes());
+		h.finalize()
+	}
+
+	// what changed from self to other.
+	pub fn diff(&self, other: &Manifest) -> Vec<Difference> {
+		let mut out = vec![];
+		for (p, d) in &self.files {
+			match other.files.get(p) {
+				None => out.push(Difference::Missing(p.clone())),
+				Some(e) if e != d => out.push(Difference::Changed(p.clone())),
+				Some(_) => {}
+			}
+		}
+		for p in other.files.keys().filter(|p| !self.files.contains_key(*p)) {
+			out.push(Difference::Added(p.clone()));
+		}
+		out
+	}
+}
+
+impl fmt::Display for Manifest {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for (p, d) in &self.files {
+			writeln!(f, "{}  {}", d, p)?;
+		}
+		Ok(())
+	}
+}
+
+impl FromStr for Manifest {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Manifest, Error> {
+		let mut m = Manifest::default();
+		for line in s.lines() {
+			let (hex, path) = line.split_once("  ").ok_or(Error::InvalidEncoding)?;
+			if hex.len() != 2 * DIGEST_LEN || !hex.bytes().all(|c| c.is_ascii_hexdigit()) || path.is_empty() {
+				return Err(Error::InvalidEncoding);
+			}
+			let mut d = [0; DIGEST_LEN];
+			for (v, i) in d.iter_mut().zip((0 .. hex.len()).step_by(2)) {
+				*v = u8::from_str_radix(&hex[i .. i + 2], 16).map_err(|_| Error::InvalidEncoding)?;
+			}
+			m.files.insert(path.to_string(), Digest::new(d));
+		}
+		Ok(m)
+	}
+}
+
+// files are read through the hasher a buffer at a time, never whole.
+pub fn hash_dir<P: AsRef<Path>>(path: P) -> io::Result<Manifest> {
+	let mut m = Manifest::default();
+	walk(path.as_ref(), "", &mut m)?;
+	Ok(m)
+}
+
+// the differences between manifest and the tree at path now; empty when
+// they match.
+pub fn verify_dir<P: AsRef<Path>>(manifest: &Manifest, path: P) -> io::Result<Vec<Difference>> {
+	Ok(manifest.diff(&hash_dir(path)?))
+}
+
+fn walk(dir: &Path, prefix: &str, m: &mut Manifest) -> io::Result<()> {
+	let mut names = vec![];
+	for e in fs::read_dir(dir)? {
+		let e = e?;
+		let name = e.file_name().into_string().map_err(|_| bad_name())?;
+		if name.contains('\n') {
+			return Err(bad_name());
+		}
+		names.push((name, e.file_type()?));
+	}
+	names.sort_by(|a, b| a.0.cmp(&b.0));
+	for (name, kind) in names {
+		let rel = format!("{}{}", prefix, name);
+		if kind.is_dir() {
+			walk(&dir.join(&name), &format!("{}/", rel), m)?;
+		} else if kind.is_file() {
+			let mut h = SpritzHasher::new();
+			io::copy(&mut File::open(dir.join(&name))?, &mut h)?;
+			m.files.insert(rel, h.finalize());
+		}
+	}
+	Ok(())
+}
+
+fn bad_name() -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, "spritz: file name not valid UTF-8 or has a newline")
+}
+
+#[test]
+fn manifest() {
+	let root = ::std::env::temp_dir().join(format!("spritz-manifest-test-{}", ::std::process::id()));
+	fs::create_dir_all(root.join("sub")).unwrap();
+	fs::write(root.join("a.txt"), b"alpha").unwrap();
+	fs::write(root.join("sub/b.bin"), vec![7; 100_000]).unwrap();
+
+	let m = hash_dir(&root).unwrap();
+	assert_eq!(m.files().map(|(p, _)| p).collect::<Vec<_>>(), ["a.txt", "sub/b.bin"]);
+	assert_eq!(m.get("a.txt").unwrap().as_bytes(), &::Spritz::hash256(b"alpha")[..]);
+	let text = m.to_string();
+	assert_eq!(text.parse::<Manifest>().unwrap(), m);
+	assert_eq!(text.parse::<Manifest>().unwrap().digest(), m.digest());
+	assert_eq!("xyz  a".parse::<Manifest>(), Err(Error::InvalidEncoding));
+	assert!(verify_dir(&m, &root).unwrap().is_empty());
+
+	fs::write(root.join("a.txt"), b"alpha!").unwrap();
+	fs::remove_file(root.join("sub/b.bin")).unwrap();
+	fs::write(root.join("c"), b"").unwrap();
+	assert_eq!(
+		verify_dir(&m, &root).unwrap(),
+		[Difference::Changed("a.txt".into()), Difference::Missing("sub/b.bin".into()), Difference::Added("c".into())]
+	);
+	assert!(hash_dir(&root).unwrap().digest() != m.digest());
+	fs::remove_dir_all(&root).unwrap();
+}
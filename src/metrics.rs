@@ -0,0 +1,106 @@
+// counters for operators watching how the cipher is used: a messages count
+// that stops moving while bytes go on, or failures that start climbing, is
+// worth looking at. they count work done, never what it was done on.
+//
+// SpritzAead is used through &self, so its counters are atomics, relaxed:
+// each is exact, but a metrics() taken while other threads seal may see
+// one counter's update before another's. targets without 64-bit atomics
+// count in usize.
+
+#[cfg(all(feature = "aead", target_has_atomic = "64"))]
+use core::sync::atomic::AtomicU64 as Count;
+#[cfg(all(feature = "aead", not(target_has_atomic = "64")))]
+use core::sync::atomic::AtomicUsize as Count;
+#[cfg(feature = "aead")]
+use core::fmt;
+#[cfg(feature = "aead")]
+use core::sync::atomic::Ordering;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Metrics {
+	// encrypted, decrypted or generated.
+	pub bytes: u64,
+	// sealed or opened, for the AEAD.
+	pub messages: u64,
+	pub shuffles: u64,
+	pub reseeds: u64,
+	// tags that didn't match.
+	pub failures: u64,
+}
+
+#[cfg(feature = "aead")]
+#[derive(Default)]
+pub(crate) struct Counters {
+	bytes: Count,
+	messages: Count,
+	shuffles: Count,
+	failures: Count,
+}
+
+#[cfg(feature = "aead")]
+impl Counters {
+	pub(crate) fn add(&self, m: Metrics) {
+		let pairs = [(&self.bytes, m.bytes), (&self.messages, m.messages), (&self.shuffles, m.shuffles), (&self.failures, m.failures)];
+		for (c, v) in pairs {
+			if v > 0 {
+				c.fetch_add(v as _, Ordering::Relaxed);
+			}
+		}
+	}
+
+	// the cast is from usize without 64-bit atomics.
+	#[allow(clippy::unnecessary_cast)]
+	pub(crate) fn get(&self) -> Metrics {
+		let get = |c: &Count| c.load(Ordering::Relaxed) as u64;
+		Metrics {
+			bytes: get(&self.bytes),
+			messages: get(&self.messages),
+			shuffles: get(&self.shuffles),
+			reseeds: 0,
+			failures: get(&self.failures),
+		}
+	}
+}
+
+// a clone starts from the counts so far, like the state it's cloned with.
+#[cfg(feature = "aead")]
+impl Clone for Counters {
+	fn clone(&self) -> Counters {
+		let c = Counters::default();
+		c.add(self.get());
+		c
+	}
+}
+
+#[cfg(feature = "aead")]
+impl fmt::Debug for Counters {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.get().fmt(f)
+	}
+}
+
+#[cfg(all(feature = "cipher", feature = "aead", feature = "rng"))]
+#[test]
+fn metrics() {
+	use key::{Key, Nonce};
+	use {Spritz, SpritzAead, SpritzRng};
+
+	let a = SpritzAead::new(&Key::new([1; 32]));
+	let mut buf = [0; 100];
+	let tag = a.seal_in_place_detached(&Nonce::from_counter(1), b"", &mut buf);
+	let _ = a.open_in_place_detached(&Nonce::from_counter(2), b"", &mut buf, &tag);
+	let m = a.metrics();
+	assert_eq!((m.bytes, m.messages, m.failures), (200, 2, 1));
+	assert!(m.shuffles >= 2);
+	assert_eq!(a.clone().metrics(), m);
+
+	let mut rng = SpritzRng::new(b"seed");
+	rng.next_u64();
+	rng.reseed(b"more");
+	let m = rng.metrics();
+	assert_eq!((m.bytes, m.reseeds), (8, 1));
+
+	let mut e = Spritz::with_key(&Key::new([1; 32])).nonce(&Nonce::from_counter(1));
+	e.apply_keystream(&mut buf);
+	assert_eq!(e.metrics().bytes, 100);
+}
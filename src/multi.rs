@@ -0,0 +1,108 @@
+// experimental: eight independent Spritz keystreams stepped together.
+//
+// the S tables are interleaved, S[x][lane], and the registers held as one
+// array per register, so each step of update/output is a loop over the
+// lanes that the compiler is free to vectorize. the lanes never interact:
+// each produces exactly the keystream its Spritz would have.
+
+use cipher::Encryptor;
+use error::{Error, Result};
+use N;
+#[cfg(all(test, feature = "std"))]
+use Spritz;
+
+pub const LANES: usize = 8;
+
+pub struct SpritzX8 {
+	S: [[u8; LANES]; N],
+	i: [u8; LANES],
+	j: [u8; LANES],
+	k: [u8; LANES],
+	z: [u8; LANES],
+	w: [u8; LANES],
+}
+
+impl SpritzX8 {
+	// lane n continues the keystream of states[n].
+	pub fn new(states: [Encryptor; LANES]) -> SpritzX8 {
+		let mut m = SpritzX8 { S: [[0; LANES]; N], i: [0; LANES], j: [0; LANES], k: [0; LANES], z: [0; LANES], w: [0; LANES] };
+		for (l, Encryptor { mut sp }) in IntoIterator::into_iter(states).enumerate() {
+			// the shuffle drip() would do first.
+			if sp.a > 0 {
+				sp.shuffle();
+			}
+			for (x, v) in sp.S.iter().enumerate() {
+				m.S[x][l] = *v;
+			}
+			m.i[l] = sp.i;
+			m.j[l] = sp.j;
+			m.k[l] = sp.k;
+			m.z[l] = sp.z;
+			m.w[l] = sp.w;
+		}
+		m
+	}
+
+	// one keystream byte per lane. the loops index several arrays by lane on
+	// purpose, to keep each one a plain lane-by-lane pass.
+	#[allow(clippy::needless_range_loop)]
+	fn step(&mut self) -> [u8; LANES] {
+		let S = &mut self.S;
+		for l in 0 .. LANES {
+			self.i[l] = self.i[l].wrapping_add(self.w[l]);
+		}
+		for l in 0 .. LANES {
+			let idx = self.j[l].wrapping_add(S[self.i[l] as usize][l]);
+			self.j[l] = self.k[l].wrapping_add(S[idx as usize][l]);
+		}
+		for l in 0 .. LANES {
+			self.k[l] = self.i[l].wrapping_add(self.k[l]).wrapping_add(S[self.j[l] as usize][l]);
+		}
+		for l in 0 .. LANES {
+			let (a, b) = (self.i[l] as usize, self.j[l] as usize);
+			let t = S[a][l];
+			S[a][l] = S[b][l];
+			S[b][l] = t;
+		}
+		for l in 0 .. LANES {
+			let t0 = S[self.z[l].wrapping_add(self.k[l]) as usize][l];
+			let t1 = S[self.i[l].wrapping_add(t0) as usize][l];
+			self.z[l] = S[self.j[l].wrapping_add(t1) as usize][l];
+		}
+		self.z
+	}
+
+	// xor each lane's keystream into its buffer. the buffers must all be
+	// the same length.
+	pub fn apply_keystream(&mut self, bufs: &mut [&mut [u8]; LANES]) -> Result<()> {
+		let len = bufs[0].len();
+		if !bufs.iter().all(|b| b.len() == len) {
+			return Err(Error::InvalidLength);
+		}
+		for x in 0 .. len {
+			let z = self.step();
+			for (b, v) in bufs.iter_mut().zip(z.iter()) {
+				b[x] ^= v;
+			}
+		}
+		Ok(())
+	}
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn lanes() {
+	let keys: Vec<Vec<u8>> = (0 .. LANES).map(|l| vec![l as u8; l * 20]).collect();
+	let states: [Encryptor; LANES] = ::std::array::from_fn(|l| Spritz::with_raw_key(&keys[l]).iv(b"iv"));
+	let mut m = SpritzX8::new(states);
+	let mut data = [[0u8; 100]; LANES];
+	{
+		let mut bufs = data.each_mut().map(|b| &mut b[..]);
+		m.apply_keystream(&mut bufs).unwrap();
+	}
+	for (l, d) in data.iter().enumerate() {
+		let mut want = [0; 100];
+		Spritz::new_with_iv(&keys[l], b"iv").apply_keystream(&mut want);
+		assert_eq!(d[..], want[..]);
+	}
+}
@@ -0,0 +1,84 @@
+// keystream generated ahead of use on a worker thread.
+//
+// the worker keeps up to `depth` blocks waiting in a bounded channel, so a
+// caller encrypting small packets only xors already computed bytes, and the
+// cipher work happens in between. the keystream is the same one the
+// Encryptor would have given directly.
+
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::thread::{self, JoinHandle};
+
+use cipher::Encryptor;
+use wipe_temp;
+#[cfg(test)]
+use Spritz;
+
+pub const PIPELINE_BLOCK: usize = 4096;
+
+pub struct KeystreamPipeline {
+	rx: Option<Receiver<Box<[u8; PIPELINE_BLOCK]>>>,
+	worker: Option<JoinHandle<()>>,
+	block: Box<[u8; PIPELINE_BLOCK]>,
+	pos: usize,
+}
+
+impl KeystreamPipeline {
+	pub fn new(mut e: Encryptor, depth: usize) -> KeystreamPipeline {
+		let (tx, rx) = sync_channel(depth.max(1));
+		let worker = thread::spawn(move || loop {
+			let mut b = Box::new([0; PIPELINE_BLOCK]);
+			e.sp.squeeze_into(&mut b[..]);
+			// the receiver is gone once the pipeline is dropped.
+			if tx.send(b).is_err() {
+				return;
+			}
+		});
+		KeystreamPipeline { rx: Some(rx), worker: Some(worker), block: Box::new([0; PIPELINE_BLOCK]), pos: PIPELINE_BLOCK }
+	}
+
+	pub fn apply_keystream(&mut self, buf: &mut [u8]) {
+		let mut rest = buf;
+		while !rest.is_empty() {
+			if self.pos == PIPELINE_BLOCK {
+				wipe_temp(&mut self.block[..]);
+				// the worker only stops when the receiver is dropped.
+				self.block = self.rx.as_ref().unwrap().recv().unwrap();
+				self.pos = 0;
+			}
+			let n = (PIPELINE_BLOCK - self.pos).min(rest.len());
+			let (now, later) = rest.split_at_mut(n);
+			for (v, k) in now.iter_mut().zip(self.block[self.pos ..].iter()) {
+				*v ^= k;
+			}
+			self.pos += n;
+			rest = later;
+		}
+	}
+}
+
+impl Drop for KeystreamPipeline {
+	fn drop(&mut self) {
+		if let Some(rx) = self.rx.take() {
+			for mut b in rx.try_iter() {
+				wipe_temp(&mut b[..]);
+			}
+		}
+		if let Some(w) = self.worker.take() {
+			let _ = w.join();
+		}
+		wipe_temp(&mut self.block[..]);
+	}
+}
+
+#[test]
+fn pipeline() {
+	let mut p = KeystreamPipeline::new(Spritz::with_raw_key(b"key").deterministic(), 2);
+	let mut sp = Spritz::with_raw_key(b"key").deterministic();
+	for len in [1, 100, PIPELINE_BLOCK, 3 * PIPELINE_BLOCK + 7, 0, 5] {
+		let mut a = vec![0x42; len];
+		let mut b = a.clone();
+		p.apply_keystream(&mut a);
+		sp.apply_keystream(&mut b);
+		assert!(a == b);
+	}
+}
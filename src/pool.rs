@@ -0,0 +1,76 @@
+// keyed cipher states shared between request handlers.
+//
+// key setup is most of the cost of sealing a small message. the pool keeps
+// a SpritzAead per key, each already past key setup, and it is only read
+// after being filled, so an Arc<SpritzPool> can be used from any number of
+// threads without a lock. keys are looked up by fingerprint.
+
+use std::collections::HashMap;
+
+use aead::SpritzAead;
+use key::{Fingerprint, Key};
+
+#[derive(Clone, Default)]
+pub struct SpritzPool {
+	ciphers: HashMap<Fingerprint, SpritzAead>,
+}
+
+impl SpritzPool {
+	pub fn new() -> SpritzPool {
+		SpritzPool::default()
+	}
+
+	pub fn insert(&mut self, key: &Key) -> Fingerprint {
+		let id = key.fingerprint();
+		self.ciphers.entry(id).or_insert_with(|| SpritzAead::new(key));
+		id
+	}
+
+	pub fn remove(&mut self, id: &Fingerprint) -> bool {
+		self.ciphers.remove(id).is_some()
+	}
+
+	// every seal/open on the result starts from a copy of the keyed state.
+	pub fn get(&self, id: &Fingerprint) -> Option<&SpritzAead> {
+		self.ciphers.get(id)
+	}
+
+	pub fn len(&self) -> usize {
+		self.ciphers.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.ciphers.is_empty()
+	}
+}
+
+#[test]
+fn pool() {
+	use std::sync::Arc;
+	use std::thread;
+	use key::Nonce;
+
+	let key = Key::new([4; 32]);
+	let mut pool = SpritzPool::new();
+	let id = pool.insert(&key);
+	assert_eq!(pool.insert(&key), id);
+	pool.insert(&Key::new([5; 32]));
+	assert_eq!(pool.len(), 2);
+
+	let n = Nonce::from_counter(1);
+	let want = SpritzAead::new(&key).seal(&n, b"ad", b"msg");
+	let pool = Arc::new(pool);
+	let handles: Vec<_> = (0 .. 4)
+		.map(|_| {
+			let pool = pool.clone();
+			thread::spawn(move || pool.get(&id).unwrap().seal(&n, b"ad", b"msg"))
+		})
+		.collect();
+	for h in handles {
+		assert_eq!(h.join().unwrap(), want);
+	}
+
+	let mut pool = Arc::try_unwrap(pool).ok().unwrap();
+	assert!(pool.remove(&id));
+	assert!(pool.get(&id).is_none());
+}
@@ -0,0 +1,118 @@
+// proof of work: find a nonce so that hash256(prefix || nonce) starts with
+// difficulty_bits zero bits. the nonce is 8 bytes little endian, so there
+// are only 2^64 to try: a difficulty over 64 bits is OutOfDomain, and a
+// search that runs out of nonces ends with Exhausted rather than wrapping
+// round and going on forever.
+
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+#[cfg(feature = "std")]
+use std::thread;
+
+use error::{Error, Result};
+use Spritz;
+
+pub const MAX_DIFFICULTY: u32 = 64;
+
+fn leading_zeros(h: &[u8]) -> u32 {
+	let mut n = 0;
+	for b in h {
+		n += b.leading_zeros();
+		if *b != 0 {
+			break;
+		}
+	}
+	n
+}
+
+fn check(msg: &mut Vec<u8>, prefix_len: usize, nonce: u64, difficulty_bits: u32) -> bool {
+	msg.truncate(prefix_len);
+	msg.extend_from_slice(&nonce.to_le_bytes());
+	let h: [u8; 32] = Spritz::hash_array(msg);
+	leading_zeros(&h) >= difficulty_bits
+}
+
+pub fn verify(prefix: &[u8], difficulty_bits: u32, nonce: u64) -> bool {
+	check(&mut prefix.to_vec(), prefix.len(), nonce, difficulty_bits)
+}
+
+pub fn solve(prefix: &[u8], difficulty_bits: u32) -> Result<u64> {
+	solve_from(prefix, difficulty_bits, 0)
+}
+
+fn solve_from(prefix: &[u8], difficulty_bits: u32, start: u64) -> Result<u64> {
+	if difficulty_bits > MAX_DIFFICULTY {
+		return Err(Error::OutOfDomain);
+	}
+	let mut msg = prefix.to_vec();
+	let mut nonce = start;
+	while !check(&mut msg, prefix.len(), nonce, difficulty_bits) {
+		nonce = nonce.checked_add(1).ok_or(Error::Exhausted)?;
+	}
+	Ok(nonce)
+}
+
+// like solve but spread over threads; returns whichever solution is found
+// first, which isn't necessarily the smallest.
+#[cfg(feature = "std")]
+pub fn solve_parallel(prefix: &[u8], difficulty_bits: u32, threads: usize) -> Result<u64> {
+	solve_parallel_from(prefix, difficulty_bits, threads, 0)
+}
+
+#[cfg(feature = "std")]
+fn solve_parallel_from(prefix: &[u8], difficulty_bits: u32, threads: usize, start: u64) -> Result<u64> {
+	if difficulty_bits > MAX_DIFFICULTY {
+		return Err(Error::OutOfDomain);
+	}
+	let threads = threads.max(1) as u64;
+	let done = AtomicBool::new(false);
+	let found = AtomicU64::new(0);
+	thread::scope(|s| {
+		for t in 0 .. threads {
+			let (done, found) = (&done, &found);
+			s.spawn(move || {
+				let mut msg = prefix.to_vec();
+				let Some(mut nonce) = start.checked_add(t) else {
+					return;
+				};
+				while !done.load(Ordering::Relaxed) {
+					if check(&mut msg, prefix.len(), nonce, difficulty_bits) {
+						if !done.swap(true, Ordering::SeqCst) {
+							found.store(nonce, Ordering::SeqCst);
+						}
+						return;
+					}
+					// this thread's share of the nonces is used up.
+					match nonce.checked_add(threads) {
+						Some(n) => nonce = n,
+						None => return,
+					}
+				}
+			});
+		}
+	});
+	if !done.load(Ordering::SeqCst) {
+		return Err(Error::Exhausted);
+	}
+	Ok(found.load(Ordering::SeqCst))
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn pow() {
+	let n = solve(b"challenge", 8).unwrap();
+	assert!(verify(b"challenge", 8, n));
+	for m in 0 .. n {
+		assert!(!verify(b"challenge", 8, m));
+	}
+	let p = solve_parallel(b"challenge", 8, 4).unwrap();
+	assert!(verify(b"challenge", 8, p));
+	assert_eq!(leading_zeros(&[0, 0x10, 0]), 11);
+	assert_eq!(solve(b"challenge", 65), Err(Error::OutOfDomain));
+	assert_eq!(solve_parallel(b"challenge", 1000, 4), Err(Error::OutOfDomain));
+	assert!(!verify(b"challenge", 257, n));
+	// the last few nonces, none of which has 64 zero bits.
+	assert_eq!(solve_from(b"challenge", 64, u64::MAX - 10), Err(Error::Exhausted));
+	assert_eq!(solve_parallel_from(b"challenge", 64, 4, u64::MAX - 10), Err(Error::Exhausted));
+}
@@ -0,0 +1,18 @@
+// the commonly used types in one import: use spritz::prelude::*;
+//
+// Error is renamed and Result left out so they don't shadow the std ones.
+
+#[cfg(feature = "aead")]
+pub use aead::SpritzAead;
+#[cfg(feature = "cipher")]
+pub use cipher::KeystreamExt;
+pub use duplex::SpritzDuplex;
+pub use error::Error as SpritzError;
+#[cfg(feature = "hash")]
+pub use hash::{Digest, SpritzHasher};
+pub use key::{Key, Nonce};
+#[cfg(feature = "mac")]
+pub use mac::{SpritzMac, Tag};
+#[cfg(feature = "rng")]
+pub use rng::SpritzRng;
+pub use Spritz;
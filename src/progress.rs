@@ -0,0 +1,270 @@
+// progress reporting and cancellation for long reads and writes.
+//
+// rather than a hook on every routine, these wrap the reader or writer a
+// routine works on, so the same callback serves hashing, sealing,
+// verifying or anything else that takes a Read/Write:
+//
+//   let len = file.metadata()?.len();
+//   io::copy(&mut ProgressReader::new(file, Some(len), bar), &mut hasher)?;
+//
+// the callback gets the bytes done so far and the total, if known.
+//
+// Cancellable works the same way: its closure is asked before every read or
+// write, and a Break fails that call with Error::Cancelled, which ends the
+// surrounding loop before the next chunk. an EncryptWriter stopped this way
+// is never finished, so whatever it wrote fails to decrypt as truncated.
+//
+// to leave nothing behind at all, write to an OutputFile: it only appears
+// at its path once commit() is called, and dropping it before then, as
+// returning the Cancelled error does, removes what was written.
+
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Read, Write};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+
+use error::Error;
+
+pub struct ProgressReader<R: Read, F: FnMut(u64, Option<u64>)> {
+	r: R,
+	done: u64,
+	total: Option<u64>,
+	on_progress: F,
+}
+
+impl<R: Read, F: FnMut(u64, Option<u64>)> ProgressReader<R, F> {
+	pub fn new(r: R, total: Option<u64>, on_progress: F) -> ProgressReader<R, F> {
+		ProgressReader { r, done: 0, total, on_progress }
+	}
+
+	pub fn done(&self) -> u64 {
+		self.done
+	}
+
+	pub fn into_inner(self) -> R {
+		self.r
+	}
+}
+
+impl<R: Read, F: FnMut(u64, Option<u64>)> Read for ProgressReader<R, F> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let n = self.r.read(buf)?;
+		if n > 0 {
+			self.done += n as u64;
+			(self.on_progress)(self.done, self.total);
+		}
+		Ok(n)
+	}
+}
+
+pub struct ProgressWriter<W: Write, F: FnMut(u64, Option<u64>)> {
+	w: W,
+	done: u64,
+	total: Option<u64>,
+	on_progress: F,
+}
+
+impl<W: Write, F: FnMut(u64, Option<u64>)> ProgressWriter<W, F> {
+	pub fn new(w: W, total: Option<u64>, on_progress: F) -> ProgressWriter<W, F> {
+		ProgressWriter { w, done: 0, total, on_progress }
+	}
+
+	pub fn done(&self) -> u64 {
+		self.done
+	}
+
+	// e.g. to call EncryptWriter::finish once everything is written.
+	pub fn into_inner(self) -> W {
+		self.w
+	}
+}
+
+impl<W: Write, F: FnMut(u64, Option<u64>)> Write for ProgressWriter<W, F> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let n = self.w.write(buf)?;
+		if n > 0 {
+			self.done += n as u64;
+			(self.on_progress)(self.done, self.total);
+		}
+		Ok(n)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.w.flush()
+	}
+}
+
+pub struct Cancellable<T, F: FnMut() -> ControlFlow<()>> {
+	inner: T,
+	check: F,
+}
+
+impl<T, F: FnMut() -> ControlFlow<()>> Cancellable<T, F> {
+	// for a flag set from another thread:
+	// Cancellable::new(r, || if flag.load(Relaxed) { Break(()) } else { Continue(()) })
+	pub fn new(inner: T, check: F) -> Cancellable<T, F> {
+		Cancellable { inner, check }
+	}
+
+	pub fn into_inner(self) -> T {
+		self.inner
+	}
+
+	fn check(&mut self) -> io::Result<()> {
+		match (self.check)() {
+			ControlFlow::Continue(()) => Ok(()),
+			ControlFlow::Break(()) => Err(Error::Cancelled.into()),
+		}
+	}
+}
+
+impl<R: Read, F: FnMut() -> ControlFlow<()>> Read for Cancellable<R, F> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		self.check()?;
+		self.inner.read(buf)
+	}
+}
+
+impl<W: Write, F: FnMut() -> ControlFlow<()>> Write for Cancellable<W, F> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.check()?;
+		self.inner.write(buf)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.flush()
+	}
+}
+
+// a file written under path + ".spritz-tmp" and renamed into place by
+// commit(), so it is either complete or absent.
+pub struct OutputFile {
+	w: BufWriter<File>,
+	path: PathBuf,
+	tmp: PathBuf,
+	done: bool,
+}
+
+impl OutputFile {
+	pub fn create<P: AsRef<Path>>(path: P) -> io::Result<OutputFile> {
+		let path = path.as_ref().to_path_buf();
+		let mut tmp = OsString::from(path.as_os_str());
+		tmp.push(".spritz-tmp");
+		let tmp = PathBuf::from(tmp);
+		// a new file, so a symbolic link left at tmp isn't written through.
+		match fs::remove_file(&tmp) {
+			Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+			_ => {}
+		}
+		let w = BufWriter::new(OpenOptions::new().write(true).create_new(true).open(&tmp)?);
+		Ok(OutputFile { w, path, tmp, done: false })
+	}
+
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	pub fn commit(mut self) -> io::Result<()> {
+		self.w.flush()?;
+		fs::rename(&self.tmp, &self.path)?;
+		self.done = true;
+		Ok(())
+	}
+}
+
+impl Write for OutputFile {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.w.write(buf)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.w.flush()
+	}
+}
+
+impl Drop for OutputFile {
+	fn drop(&mut self) {
+		if !self.done {
+			let _ = fs::remove_file(&self.tmp);
+		}
+	}
+}
+
+#[cfg(all(feature = "hash", feature = "aead"))]
+#[test]
+fn progress() {
+	use hash::SpritzHasher;
+	use key::{Key, Nonce};
+	use stream::EncryptWriter;
+
+	let data = vec![9; 10000];
+	let mut seen = vec![];
+	let mut h = SpritzHasher::new();
+	io::copy(&mut ProgressReader::new(&data[..], Some(10000), |d, t| seen.push((d, t))), &mut h).unwrap();
+	assert_eq!(h.finalize().as_bytes(), &::Spritz::hash256(&data)[..]);
+	assert_eq!(seen.last(), Some(&(10000, Some(10000))));
+	assert!(seen.windows(2).all(|w| w[0].0 < w[1].0));
+
+	let mut last = 0;
+	let enc = EncryptWriter::new(vec![], &Key::new([1; 32]), &Nonce::from_counter(1));
+	let mut w = ProgressWriter::new(enc, None, |d, _| last = d);
+	w.write_all(&data).unwrap();
+	assert!(w.into_inner().finish().is_ok());
+	assert_eq!(last, 10000);
+}
+
+#[test]
+fn cancellable() {
+	use duplex::SpritzDuplex;
+
+	let data = vec![9; 100000];
+	let mut reads = 0;
+	let r = Cancellable::new(&data[..], || {
+		reads += 1;
+		if reads > 3 { ControlFlow::Break(()) } else { ControlFlow::Continue(()) }
+	});
+	let e = SpritzDuplex::new(b"").absorb_reader(r).unwrap_err();
+	assert_eq!(e.kind(), io::ErrorKind::Other);
+	assert_eq!(e.get_ref().unwrap().downcast_ref::<Error>(), Some(&Error::Cancelled));
+	assert_eq!(reads, 4);
+}
+
+#[test]
+fn output_file() {
+	let path = ::std::env::temp_dir().join(format!("spritz-output-test-{}", ::std::process::id()));
+	let tmp = PathBuf::from(format!("{}.spritz-tmp", path.display()));
+
+	let mut writes = 0;
+	let mut w = Cancellable::new(OutputFile::create(&path).unwrap(), || {
+		writes += 1;
+		if writes > 2 { ControlFlow::Break(()) } else { ControlFlow::Continue(()) }
+	});
+	let e = (0 .. 10).try_for_each(|_| w.write_all(&[9; 1000])).unwrap_err();
+	assert_eq!(e.get_ref().unwrap().downcast_ref::<Error>(), Some(&Error::Cancelled));
+	assert!(tmp.exists());
+	drop(w);
+	assert!(!tmp.exists() && !path.exists());
+
+	let mut f = OutputFile::create(&path).unwrap();
+	f.write_all(b"done").unwrap();
+	assert!(!path.exists());
+	f.commit().unwrap();
+	assert_eq!(fs::read(&path).unwrap(), b"done");
+	assert!(!tmp.exists());
+
+	// a link where the temporary file goes is replaced, not followed.
+	#[cfg(unix)]
+	{
+		let target = PathBuf::from(format!("{}.target", path.display()));
+		fs::write(&target, b"keep").unwrap();
+		::std::os::unix::fs::symlink(&target, &tmp).unwrap();
+		let mut f = OutputFile::create(&path).unwrap();
+		f.write_all(b"new").unwrap();
+		f.commit().unwrap();
+		assert_eq!(fs::read(&target).unwrap(), b"keep");
+		assert_eq!(fs::read(&path).unwrap(), b"new");
+		fs::remove_file(&target).unwrap();
+	}
+	fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,122 @@
+// STROBE-style protocol framework on top of the duplex.
+//
+// every operation starts with an absorb_stop and an operation byte, so the
+// transcript is unambiguous without length prefixes: ad(b"ab"); ad(b"c") is
+// not the same as ad(b"abc"). both sides must run the same sequence of
+// operations. enc/mac are only secret after a key() (or a shared ad()).
+
+use duplex::SpritzDuplex;
+use error::Error;
+
+const OP_AD: u8 = 1;
+const OP_KEY: u8 = 2;
+const OP_PRF: u8 = 3;
+const OP_ENC: u8 = 4;
+const OP_MAC: u8 = 5;
+const OP_RATCHET: u8 = 6;
+
+#[derive(Clone, Debug)]
+pub struct Protocol {
+	d: SpritzDuplex,
+}
+
+impl Protocol {
+	pub fn new(label: &[u8]) -> Protocol {
+		Protocol { d: SpritzDuplex::new(label) }
+	}
+
+	fn begin(&mut self, op: u8) {
+		self.d.absorb_stop();
+		self.d.absorb(&[op]);
+	}
+
+	// absorb associated data both sides already know.
+	pub fn ad(&mut self, data: &[u8]) {
+		self.begin(OP_AD);
+		self.d.absorb(data);
+	}
+
+	// absorb a shared secret.
+	pub fn key(&mut self, key: &[u8]) {
+		self.begin(OP_KEY);
+		self.d.absorb(key);
+	}
+
+	// fill out with pseudorandom bytes bound to everything so far.
+	pub fn prf(&mut self, out: &mut [u8]) {
+		self.begin(OP_PRF);
+		self.d.squeeze(out);
+	}
+
+	pub fn send_enc(&mut self, buf: &mut [u8]) {
+		self.begin(OP_ENC);
+		self.d.seal(buf);
+	}
+
+	// the plaintext is unauthenticated until a following recv_mac succeeds.
+	pub fn recv_enc(&mut self, buf: &mut [u8]) {
+		self.begin(OP_ENC);
+		self.d.open(buf);
+	}
+
+	pub fn send_mac(&mut self, tag: &mut [u8]) {
+		self.begin(OP_MAC);
+		self.d.squeeze(tag);
+	}
+
+	pub fn recv_mac(&mut self, tag: &[u8]) -> Result<(), Error> {
+		self.begin(OP_MAC);
+		if self.d.verify(tag) {
+			Ok(())
+		} else {
+			Err(Error::InvalidTag)
+		}
+	}
+
+	// forward secrecy: the old state can't be recovered from the new one.
+	pub fn ratchet(&mut self) {
+		self.begin(OP_RATCHET);
+		self.d.ratchet();
+	}
+}
+
+#[test]
+fn protocol() {
+	let mut a = Protocol::new(b"test protocol");
+	let mut b = Protocol::new(b"test protocol");
+	for p in [&mut a, &mut b] {
+		p.key(b"shared secret");
+		p.ad(b"hello");
+	}
+
+	let msg = b"attack at dawn".to_vec();
+	let mut buf = msg.clone();
+	a.send_enc(&mut buf);
+	let mut tag = [0; 16];
+	a.send_mac(&mut tag);
+	assert!(buf != msg);
+
+	b.recv_enc(&mut buf);
+	assert_eq!(buf, msg);
+	assert_eq!(b.recv_mac(&tag), Ok(()));
+
+	a.ratchet();
+	b.ratchet();
+	let (mut x, mut y) = ([0; 8], [0; 8]);
+	a.prf(&mut x);
+	b.prf(&mut y);
+	assert_eq!(x, y);
+
+	a.send_mac(&mut tag);
+	tag[0] ^= 1;
+	assert_eq!(b.recv_mac(&tag), Err(Error::InvalidTag));
+
+	let mut c = Protocol::new(b"test protocol");
+	let mut d = Protocol::new(b"test protocol");
+	c.ad(b"ab");
+	c.ad(b"c");
+	d.ad(b"abc");
+	c.prf(&mut x);
+	d.prf(&mut y);
+	assert!(x != y);
+}
@@ -0,0 +1,210 @@
+// spritz on a permutation of N elements, for N a power of two from 16 to
+// 256, following the pseudocode in section 2 of the paper with every sum
+// taken mod N.
+//
+// this is for analysing the reduced versions and for devices where the
+// 256-byte state is too much. small N is much weaker, and SpritzN<256> is
+// only a slower Spritz. symbols, both absorbed and squeezed, are values in
+// [0, N); an absorbed symbol is split into two digits base D = ceil(sqrt N),
+// which for N = 256 are the nibbles Spritz uses.
+
+use error::{Error, Result};
+use sponge::SpongePermutation;
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct SpritzN<const N: usize> {
+	S: [u8; N],
+	i: u8,
+	j: u8,
+	k: u8,
+	z: u8,
+	a: u8,
+	w: u8,
+}
+
+const fn ceil_sqrt(n: usize) -> usize {
+	let mut d = 1;
+	while d * d < n {
+		d += 1;
+	}
+	d
+}
+
+impl<const N: usize> SpritzN<N> {
+	const D: usize = ceil_sqrt(N);
+
+	pub fn new(key: &[u8]) -> SpritzN<N> {
+		let mut sp = SpritzN::initialize_state();
+		sp.absorb(key);
+		sp
+	}
+
+	pub fn initialize_state() -> SpritzN<N> {
+		const { assert!(N.is_power_of_two() && N >= 16 && N <= 256, "spritz: N must be a power of two from 16 to 256") };
+		let mut S = [0; N];
+		for (i, v) in S.iter_mut().enumerate() {
+			*v = i as u8;
+		}
+		SpritzN { S, i: 0, j: 0, k: 0, z: 0, a: 0, w: 1 }
+	}
+
+	// the hash with r = out.len(), which is absorbed as a symbol and so
+	// must be under N.
+	pub fn hash_into(msg: &[u8], out: &mut [u8]) -> Result<()> {
+		if out.len() >= N {
+			return Err(Error::InvalidLength);
+		}
+		let mut sp = SpritzN::<N>::initialize_state();
+		sp.absorb(msg);
+		sp.absorb_stop();
+		sp.absorb(&[out.len() as u8]);
+		sp.squeeze_into(out);
+		Ok(())
+	}
+
+	// panics on a symbol outside [0, N).
+	pub fn absorb(&mut self, symbols: &[u8]) {
+		for &b in symbols {
+			assert!((b as usize) < N, "spritz: symbol out of range");
+			self.absorb_nibble(b as usize % SpritzN::<N>::D);
+			self.absorb_nibble(b as usize / SpritzN::<N>::D);
+		}
+	}
+
+	fn absorb_nibble(&mut self, x: usize) {
+		if self.a as usize == N / 2 {
+			self.shuffle();
+		}
+		self.S.swap(self.a as usize, N / 2 + x);
+		self.a = self.add(self.a, 1);
+	}
+
+	pub fn absorb_stop(&mut self) {
+		if self.a as usize == N / 2 {
+			self.shuffle();
+		}
+		self.a = self.add(self.a, 1);
+	}
+
+	fn shuffle(&mut self) {
+		self.whip(2 * N);
+		self.crush();
+		self.whip(2 * N);
+		self.crush();
+		self.whip(2 * N);
+		self.a = 0;
+	}
+
+	fn whip(&mut self, r: usize) {
+		for _ in 0 .. r {
+			self.update();
+		}
+		// the next odd w, which is the next one coprime to N.
+		self.w = self.add(self.w, 2);
+	}
+
+	// branchless, as in Spritz::crush.
+	fn crush(&mut self) {
+		for v in 0 .. N / 2 {
+			let (x, y) = (self.S[v], self.S[N - 1 - v]);
+			let m = ((y as u16).wrapping_sub(x as u16) >> 8) as u8;
+			let d = (x ^ y) & m;
+			self.S[v] = x ^ d;
+			self.S[N - 1 - v] = y ^ d;
+		}
+	}
+
+	pub fn squeeze_into(&mut self, out: &mut [u8]) {
+		for v in out.iter_mut() {
+			*v = self.drip();
+		}
+	}
+
+	pub fn drip(&mut self) -> u8 {
+		if self.a > 0 {
+			self.shuffle();
+		}
+		self.update();
+		self.output()
+	}
+
+	fn update(&mut self) {
+		self.i = self.add(self.i, self.w);
+		self.j = self.add(self.k, self.S[self.add(self.j, self.S[self.i as usize]) as usize]);
+		self.k = self.add(self.add(self.i, self.k), self.S[self.j as usize]);
+		self.S.swap(self.i as usize, self.j as usize);
+	}
+
+	fn output(&mut self) -> u8 {
+		let t0 = self.S[self.add(self.z, self.k) as usize];
+		let t1 = self.S[self.add(self.i, t0) as usize];
+		self.z = self.S[self.add(self.j, t1) as usize];
+		self.z
+	}
+
+	fn add(&self, x: u8, y: u8) -> u8 {
+		((x as usize + y as usize) & (N - 1)) as u8
+	}
+}
+
+// SpritzN<256> absorbs bytes and squeezes what Spritz does, so it can stand
+// in for it in the modes.
+impl SpongePermutation for SpritzN<256> {
+	fn initialize() -> SpritzN<256> {
+		SpritzN::initialize_state()
+	}
+
+	fn absorb(&mut self, data: &[u8]) {
+		SpritzN::absorb(self, data);
+	}
+
+	fn absorb_stop(&mut self) {
+		SpritzN::absorb_stop(self);
+	}
+
+	fn squeeze(&mut self, out: &mut [u8]) {
+		self.squeeze_into(out);
+	}
+
+	fn wipe(&mut self) {
+		::zeroize(&mut self.S);
+		let regs = [&mut self.i, &mut self.j, &mut self.k, &mut self.z, &mut self.a, &mut self.w];
+		for r in regs {
+			::zeroize(::core::slice::from_mut(r));
+		}
+	}
+}
+
+#[cfg(all(feature = "alloc", feature = "hash"))]
+#[test]
+fn reduced() {
+	use Spritz;
+
+	let mut a = SpritzN::<256>::new(b"arcfour");
+	let mut b = Spritz::new(b"arcfour");
+	let mut x = [0; 300];
+	a.squeeze_into(&mut x);
+	assert_eq!(x[..], b.squeeze(300)[..]);
+	let mut h = [0; 32];
+	SpritzN::<256>::hash_into(b"ABC", &mut h).unwrap();
+	assert_eq!(h[..], Spritz::hash256(b"ABC")[..]);
+	assert_eq!(SpritzN::<16>::hash_into(b"", &mut [0; 16]), Err(Error::InvalidLength));
+
+	let mut s = SpritzN::<16>::new(&[1, 2, 3, 15]);
+	s.absorb_stop();
+	s.absorb(&[7; 20]);
+	let mut out = [0; 64];
+	s.squeeze_into(&mut out);
+	assert!(out.iter().all(|v| *v < 16));
+	let mut seen = [false; 16];
+	for v in &s.S {
+		seen[*v as usize] = true;
+	}
+	assert!(seen.iter().all(|v| *v));
+	let mut t = SpritzN::<16>::new(&[1, 2, 3, 14]);
+	t.absorb_stop();
+	t.absorb(&[7; 20]);
+	let mut o = [0; 64];
+	t.squeeze_into(&mut o);
+	assert!(o != out);
+}
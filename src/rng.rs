@@ -0,0 +1,91 @@
+// keyed random generator on the spritz keystream.
+
+#[cfg(all(feature = "std", not(target_os = "wasi")))]
+use std::fs::File;
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(all(feature = "std", not(target_os = "wasi")))]
+use std::io::Read;
+
+use metrics::Metrics;
+use Spritz;
+
+#[cfg(feature = "std")]
+const OS_SEED_LEN: usize = 32;
+
+#[derive(Clone, Debug)]
+pub struct SpritzRng {
+	sp: Spritz,
+	reseeds: u64,
+}
+
+impl SpritzRng {
+	// deterministic: the same seed gives the same output.
+	pub fn new(seed: &[u8]) -> SpritzRng {
+		SpritzRng { sp: Spritz::new(seed), reseeds: 0 }
+	}
+
+	// seeded from /dev/urandom, or random_get on WASI.
+	#[cfg(feature = "std")]
+	pub fn from_os() -> io::Result<SpritzRng> {
+		let mut seed = [0; OS_SEED_LEN];
+		os_random(&mut seed)?;
+		Ok(SpritzRng::new(&seed))
+	}
+
+	// mix more entropy into the state.
+	pub fn reseed(&mut self, entropy: &[u8]) {
+		self.sp.absorb_stop();
+		self.sp.absorb(entropy);
+		self.reseeds += 1;
+	}
+
+	pub fn metrics(&self) -> Metrics {
+		let u = self.sp.usage();
+		Metrics { bytes: u.squeezed, shuffles: u.shuffles, reseeds: self.reseeds, ..Metrics::default() }
+	}
+
+	pub fn fill_bytes(&mut self, out: &mut [u8]) {
+		self.sp.squeeze_into(out);
+	}
+
+	pub fn next_u32(&mut self) -> u32 {
+		let mut b = [0; 4];
+		self.fill_bytes(&mut b);
+		u32::from_le_bytes(b)
+	}
+
+	pub fn next_u64(&mut self) -> u64 {
+		let mut b = [0; 8];
+		self.fill_bytes(&mut b);
+		u64::from_le_bytes(b)
+	}
+}
+
+#[cfg(all(feature = "std", not(target_os = "wasi")))]
+fn os_random(buf: &mut [u8]) -> io::Result<()> {
+	File::open("/dev/urandom")?.read_exact(buf)
+}
+
+#[cfg(all(feature = "std", target_os = "wasi"))]
+fn os_random(buf: &mut [u8]) -> io::Result<()> {
+	#[link(wasm_import_module = "wasi_snapshot_preview1")]
+	extern "C" {
+		fn random_get(buf: *mut u8, len: usize) -> u16;
+	}
+	match unsafe { random_get(buf.as_mut_ptr(), buf.len()) } {
+		0 => Ok(()),
+		errno => Err(io::Error::from_raw_os_error(errno as i32)),
+	}
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn rng() {
+	let mut a = SpritzRng::new(b"seed");
+	let mut b = SpritzRng::new(b"seed");
+	assert_eq!(a.next_u64(), b.next_u64());
+	b.reseed(b"more");
+	assert!(a.next_u32() != b.next_u32());
+	assert!(SpritzRng::from_os().is_ok());
+}
@@ -0,0 +1,93 @@
+// a known-answer self test, for firmware that has to check the cipher
+// works before using it. the keystream and hash vectors are the paper's;
+// the MAC and AEAD ones are this crate's own, as written by `spritz kat`.
+
+use core::fmt;
+#[cfg(feature = "std")]
+use std::error;
+
+use aead::{SpritzAead, Tag};
+use key::{Key, Nonce};
+use mac::SpritzMac;
+use Spritz;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SelfTestError {
+	Keystream,
+	Hash,
+	Mac,
+	Aead,
+}
+
+impl fmt::Display for SelfTestError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let what = match *self {
+			SelfTestError::Keystream => "keystream",
+			SelfTestError::Hash => "hash",
+			SelfTestError::Mac => "mac",
+			SelfTestError::Aead => "aead",
+		};
+		write!(f, "spritz: {} self test failed", what)
+	}
+}
+
+#[cfg(feature = "std")]
+impl error::Error for SelfTestError {}
+
+const KEYSTREAM: [u8; 8] = [0x1a, 0xfa, 0x8b, 0x5e, 0xe3, 0x37, 0xdb, 0xc7];
+
+const HASH: [u8; 32] = [
+	0x02, 0x8f, 0xa2, 0xb4, 0x8b, 0x93, 0x4a, 0x18, 0x62, 0xb8, 0x69, 0x10, 0x51, 0x3a, 0x47, 0x67,
+	0x7c, 0x1c, 0x2d, 0x95, 0xec, 0x3e, 0x75, 0x70, 0x78, 0x6f, 0x1c, 0x32, 0x8b, 0xbd, 0x4a, 0x47,
+];
+
+const MAC: [u8; 32] = [
+	0x2e, 0xf6, 0xf3, 0x3b, 0x9d, 0xd4, 0x08, 0x35, 0x89, 0xbe, 0x99, 0xab, 0xf8, 0xfc, 0xae, 0x5f,
+	0x7b, 0xfe, 0x77, 0x11, 0xd4, 0xf1, 0x69, 0x52, 0xf9, 0x96, 0x2d, 0x05, 0xd5, 0x3a, 0x45, 0xb3,
+];
+
+const SEALED: [u8; 35] = [
+	0xac, 0x33, 0xb5, 0x58, 0x81, 0x36, 0xae, 0x95, 0x37, 0x21, 0x52, 0x39, 0xe1, 0xf7, 0x48, 0xb9,
+	0xf1, 0xf3, 0x14, 0x61, 0xe6, 0x3c, 0xf1, 0x87, 0x02, 0x8c, 0x4d, 0x58, 0x4c, 0x98, 0x8e, 0x49,
+	0x30, 0x2f, 0x63,
+];
+
+// runs every vector and reports the first that fails.
+pub fn self_test() -> Result<(), SelfTestError> {
+	let mut ks = [0; 8];
+	Spritz::new(b"arcfour").apply_keystream(&mut ks);
+	check(ks == KEYSTREAM, SelfTestError::Keystream)?;
+
+	let d: [u8; 32] = Spritz::hash_array(b"ABC");
+	check(d == HASH, SelfTestError::Hash)?;
+
+	let mut key = [0; 32];
+	for (i, v) in key.iter_mut().enumerate() {
+		*v = i as u8;
+	}
+	let mut m = SpritzMac::new(&key);
+	m.update(b"ABC");
+	check(m.verify(&Tag::new(MAC)).is_ok(), SelfTestError::Mac)?;
+
+	let a = SpritzAead::new(&Key::new(key));
+	let n = Nonce::from_counter(1);
+	let mut buf = *b"ABC";
+	let tag = a.seal_in_place_detached(&n, &[0, 1, 2], &mut buf);
+	check(buf[..] == SEALED[.. 3] && tag.as_bytes() == &SEALED[3 ..], SelfTestError::Aead)?;
+	let opened = a.open_in_place_detached(&n, &[0, 1, 2], &mut buf, &tag);
+	check(opened.is_ok() && buf == *b"ABC", SelfTestError::Aead)
+}
+
+fn check(ok: bool, e: SelfTestError) -> Result<(), SelfTestError> {
+	if ok {
+		Ok(())
+	} else {
+		Err(e)
+	}
+}
+
+#[test]
+fn selftest() {
+	assert_eq!(self_test(), Ok(()));
+	assert_eq!(SelfTestError::Mac.to_string(), "spritz: mac self test failed");
+}
@@ -0,0 +1,95 @@
+// what the modes need from a permutation, so the duplex, hash, MAC and AEAD
+// can run over another one and be compared with Spritz without copying
+// them. each mode's usual constructor is for Spritz, and with_permutation
+// gives the same mode over any P:
+//
+//   let h = SpritzHasher::<SpritzN<256>>::with_permutation();
+//
+// the wire formats are Spritz's only when P is Spritz, or a permutation
+// that squeezes the same bytes.
+
+use {wipe_temp, Spritz, Usage, KEYSTREAM_BLOCK};
+
+pub trait SpongePermutation: Clone {
+	// the state before anything is absorbed.
+	fn initialize() -> Self;
+	fn absorb(&mut self, data: &[u8]);
+	fn absorb_stop(&mut self);
+	fn squeeze(&mut self, out: &mut [u8]);
+	// overwrite the state with zeros; it's useless afterwards.
+	fn wipe(&mut self);
+
+	// buf xored with the next buf.len() squeezed bytes.
+	fn apply_keystream(&mut self, buf: &mut [u8]) {
+		let mut ks = [0; KEYSTREAM_BLOCK];
+		for chunk in buf.chunks_mut(KEYSTREAM_BLOCK) {
+			let ks = &mut ks[.. chunk.len()];
+			self.squeeze(ks);
+			for (v, k) in chunk.iter_mut().zip(ks.iter()) {
+				*v ^= k;
+			}
+		}
+		wipe_temp(&mut ks);
+	}
+
+	// all zeros for permutations that don't keep count.
+	fn usage(&self) -> Usage {
+		Usage::default()
+	}
+}
+
+impl SpongePermutation for Spritz {
+	fn initialize() -> Spritz {
+		Spritz::initialize_state()
+	}
+
+	fn absorb(&mut self, data: &[u8]) {
+		Spritz::absorb(self, data);
+	}
+
+	fn absorb_stop(&mut self) {
+		Spritz::absorb_stop(self);
+	}
+
+	fn squeeze(&mut self, out: &mut [u8]) {
+		self.squeeze_into(out);
+	}
+
+	fn wipe(&mut self) {
+		Spritz::wipe(self);
+	}
+
+	fn apply_keystream(&mut self, buf: &mut [u8]) {
+		Spritz::apply_keystream(self, buf);
+	}
+
+	fn usage(&self) -> Usage {
+		Spritz::usage(self)
+	}
+}
+
+#[cfg(all(feature = "alloc", feature = "hash", feature = "aead"))]
+#[test]
+fn generic_modes() {
+	use aead::SpritzAead;
+	use hash::SpritzHasher;
+	use key::{Key, Nonce};
+	use mac::SpritzMac;
+	use reduced::SpritzN;
+
+	let mut h = SpritzHasher::<SpritzN<256>>::with_permutation();
+	h.update(b"arcfour");
+	assert_eq!(h.finalize().as_bytes(), &Spritz::hash256(b"arcfour")[..]);
+
+	let mut m = SpritzMac::<SpritzN<256>>::with_permutation(b"key");
+	m.update(b"msg");
+	let mut n = SpritzMac::new(b"key");
+	n.update(b"msg");
+	assert_eq!(m.finalize(), n.finalize());
+
+	let key = Key::new([7; 32]);
+	let a = SpritzAead::<SpritzN<256>>::with_permutation(&key);
+	let sealed = a.seal(&Nonce::from_counter(1), b"ad", b"hello");
+	assert_eq!(sealed, SpritzAead::new(&key).seal(&Nonce::from_counter(1), b"ad", b"hello"));
+	assert_eq!(a.open(&Nonce::from_counter(1), b"ad", &sealed).unwrap(), b"hello");
+}
@@ -0,0 +1,115 @@
+// quick statistical checks on keystream: monobit, runs and serial from the
+// NIST and Menezes et al. batteries, and a chi-square on byte values. each
+// gives a chi-square statistic and the critical value at the 1% level, so
+// about one in a hundred good streams fails one test; a variant that always
+// fails is broken, one that passes isn't shown to be anything.
+//
+// this is a smoke test for reduced and weakened variants, not a replacement
+// for TestU01 or PractRand. bits are taken msb first.
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Test {
+	pub statistic: f64,
+	pub critical: f64,
+}
+
+impl Test {
+	pub fn passed(&self) -> bool {
+		self.statistic <= self.critical
+	}
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Report {
+	pub monobit: Test,
+	pub runs: Test,
+	pub serial: Test,
+	pub chi_square: Test,
+}
+
+impl Report {
+	pub fn passed(&self) -> bool {
+		[self.monobit, self.runs, self.serial, self.chi_square].iter().all(Test::passed)
+	}
+}
+
+// 1% critical values of chi-square with 1, 2 and 255 degrees of freedom.
+const CHI2_1: f64 = 6.635;
+const CHI2_2: f64 = 9.210;
+const CHI2_255: f64 = 310.457;
+
+fn bit(data: &[u8], n: usize) -> u8 {
+	data[n / 8] >> (7 - n % 8) & 1
+}
+
+// all four tests over data, which should be a few thousand bytes at least.
+pub fn battery(data: &[u8]) -> Report {
+	Report { monobit: monobit(data), runs: runs(data), serial: serial(data), chi_square: chi_square(data) }
+}
+
+// the excess of ones over zeros, squared over the number of bits.
+pub fn monobit(data: &[u8]) -> Test {
+	let n = 8.0 * data.len() as f64;
+	let ones: u32 = data.iter().map(|b| b.count_ones()).sum();
+	let s = 2.0 * ones as f64 - n;
+	Test { statistic: s * s / n, critical: CHI2_1 }
+}
+
+// the number of runs of equal bits against what the share of ones predicts,
+// squared. with a share too far from a half, the test doesn't apply, and it
+// fails with an infinite statistic, as in SP 800-22.
+pub fn runs(data: &[u8]) -> Test {
+	let bits = 8 * data.len();
+	let n = bits as f64;
+	let pi = data.iter().map(|b| b.count_ones()).sum::<u32>() as f64 / n;
+	if (pi - 0.5) * (pi - 0.5) >= 4.0 / n {
+		return Test { statistic: f64::INFINITY, critical: CHI2_1 };
+	}
+	let v = 1 + (1 .. bits).filter(|&k| bit(data, k) != bit(data, k - 1)).count();
+	let q = pi * (1.0 - pi);
+	let d = v as f64 - 2.0 * n * q;
+	Test { statistic: d * d / (8.0 * n * q * q), critical: CHI2_1 }
+}
+
+// the counts of overlapping bit pairs against those of single bits.
+pub fn serial(data: &[u8]) -> Test {
+	let bits = 8 * data.len();
+	let n = bits as f64;
+	let ones = data.iter().map(|b| b.count_ones()).sum::<u32>() as f64;
+	let mut pairs = [0u32; 4];
+	for k in 1 .. bits {
+		pairs[(bit(data, k - 1) << 1 | bit(data, k)) as usize] += 1;
+	}
+	let sq: f64 = pairs.iter().map(|&c| c as f64 * c as f64).sum();
+	let statistic = 4.0 / (n - 1.0) * sq - 2.0 / n * (ones * ones + (n - ones) * (n - ones)) + 1.0;
+	Test { statistic, critical: CHI2_2 }
+}
+
+// how far the byte values are from uniform.
+pub fn chi_square(data: &[u8]) -> Test {
+	let mut counts = [0u32; 256];
+	for b in data {
+		counts[*b as usize] += 1;
+	}
+	let e = data.len() as f64 / 256.0;
+	let statistic = counts.iter().map(|&c| (c as f64 - e) * (c as f64 - e) / e).sum();
+	Test { statistic, critical: CHI2_255 }
+}
+
+#[test]
+fn stats() {
+	use Spritz;
+
+	let mut ks = [0; 20000];
+	Spritz::new(b"stats").squeeze_into(&mut ks);
+	let r = battery(&ks);
+	assert!(r.passed(), "{:?}", r);
+
+	assert!(!monobit(&[0xff; 1000]).passed());
+	assert!(!runs(&[0x55; 1000]).passed());
+	assert!(monobit(&[0x55; 1000]).passed());
+	let counter: [u8; 20000] = core::array::from_fn(|i| i as u8);
+	assert!(!serial(&[0x0f; 1000]).passed());
+	assert!(chi_square(&counter).passed());
+	assert!(!chi_square(&[7; 1000]).passed());
+}
@@ -0,0 +1,914 @@
+// std::io adapters.
+
+use std::convert::TryFrom;
+use std::io::{self, BufRead, IoSlice, Read, Write};
+use std::iter::Peekable;
+use std::thread;
+
+use aead::{SpritzAead, Tag, TAG_LEN};
+#[cfg(feature = "cipher")]
+use cipher::Encryptor;
+use error::{Error, Result};
+use key::{Key, Nonce, NONCE_LEN};
+#[cfg(feature = "cipher")]
+use mac::SpritzMac;
+use Spritz;
+
+// an endless reader of keystream bytes.
+#[cfg(feature = "cipher")]
+pub struct KeystreamReader {
+	e: Encryptor,
+}
+
+#[cfg(feature = "cipher")]
+impl KeystreamReader {
+	pub fn new(e: Encryptor) -> KeystreamReader {
+		KeystreamReader { e }
+	}
+
+	pub fn into_inner(self) -> Encryptor {
+		self.e
+	}
+}
+
+#[cfg(feature = "cipher")]
+impl Read for KeystreamReader {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		self.e.sp.squeeze_into(buf);
+		Ok(buf.len())
+	}
+}
+
+// the bare keystream xored over a stream, the format older tools and the
+// CLI's --format raw write: no header and nothing to check. with_mac adds
+// a trailer, a SpritzMac tag over the ciphertext, so changes are caught,
+// though only at the end: XorReader hands out plaintext as it goes and
+// fails at EOF if the tag doesn't match. a trailer stream is the plain one
+// with TAG_LEN bytes after it, so whether to expect one has to be agreed,
+// like the key. mac_key should be independent of the cipher key.
+
+// the scratch buffer XorWriter encrypts through.
+#[cfg(feature = "cipher")]
+const XOR_BLOCK: usize = 4096;
+
+// the keystream can't be taken back, so a block is encrypted once, into
+// out, and written from there; what w doesn't take is written out at the
+// start of the next write, flush or finish, as EncryptWriter does.
+#[cfg(feature = "cipher")]
+pub struct XorWriter<W: Write> {
+	w: W,
+	e: Encryptor,
+	mac: Option<SpritzMac>,
+	// ciphertext not yet taken by w.
+	out: Vec<u8>,
+}
+
+#[cfg(feature = "cipher")]
+impl<W: Write> XorWriter<W> {
+	pub fn new(w: W, e: Encryptor) -> XorWriter<W> {
+		XorWriter { w, e, mac: None, out: Vec::with_capacity(XOR_BLOCK) }
+	}
+
+	pub fn with_mac(w: W, e: Encryptor, mac_key: &[u8]) -> XorWriter<W> {
+		XorWriter { w, e, mac: Some(SpritzMac::new(mac_key)), out: Vec::with_capacity(XOR_BLOCK) }
+	}
+
+	fn write_out(&mut self) -> io::Result<()> {
+		while !self.out.is_empty() {
+			match self.w.write(&self.out) {
+				Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+				Ok(n) => {
+					self.out.drain(.. n);
+				}
+				Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+				Err(e) => return Err(e),
+			}
+		}
+		Ok(())
+	}
+
+	// write the trailer, if there is one, and hand back the inner writer.
+	pub fn finish(mut self) -> io::Result<W> {
+		self.write_out()?;
+		if let Some(m) = self.mac.take() {
+			self.out.extend_from_slice(m.finalize().as_bytes());
+			self.write_out()?;
+		}
+		self.w.flush()?;
+		Ok(self.w)
+	}
+}
+
+#[cfg(feature = "cipher")]
+impl<W: Write> Write for XorWriter<W> {
+	fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+		let mut n = 0;
+		loop {
+			// an error once some of data is taken waits for the next call.
+			if let Err(e) = self.write_out() {
+				return if n == 0 { Err(e) } else { Ok(n) };
+			}
+			if n == data.len() {
+				return Ok(n);
+			}
+			let m = XOR_BLOCK.min(data.len() - n);
+			self.out.extend_from_slice(&data[n .. n + m]);
+			self.e.apply_keystream(&mut self.out);
+			if let Some(mac) = self.mac.as_mut() {
+				mac.update(&self.out);
+			}
+			n += m;
+		}
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.write_out()?;
+		self.w.flush()
+	}
+}
+
+#[cfg(feature = "cipher")]
+pub struct XorReader<R: Read> {
+	r: R,
+	e: Encryptor,
+	mac: Option<SpritzMac>,
+	// ciphertext read but not yet returned; the last TAG_LEN bytes of it may
+	// be the trailer.
+	held: Vec<u8>,
+	done: bool,
+}
+
+#[cfg(feature = "cipher")]
+impl<R: Read> XorReader<R> {
+	pub fn new(r: R, e: Encryptor) -> XorReader<R> {
+		XorReader { r, e, mac: None, held: vec![], done: false }
+	}
+
+	pub fn with_mac(r: R, e: Encryptor, mac_key: &[u8]) -> XorReader<R> {
+		XorReader { r, e, mac: Some(SpritzMac::new(mac_key)), held: vec![], done: false }
+	}
+}
+
+#[cfg(feature = "cipher")]
+impl<R: Read> Read for XorReader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let Some(mac) = self.mac.as_mut() else {
+			let n = self.r.read(buf)?;
+			self.e.apply_keystream(&mut buf[.. n]);
+			return Ok(n);
+		};
+		loop {
+			if self.held.len() > TAG_LEN {
+				let n = (self.held.len() - TAG_LEN).min(buf.len());
+				buf[.. n].copy_from_slice(&self.held[.. n]);
+				self.held.drain(.. n);
+				mac.update(&buf[.. n]);
+				self.e.apply_keystream(&mut buf[.. n]);
+				return Ok(n);
+			}
+			if buf.is_empty() {
+				return Ok(0);
+			}
+			if !self.done {
+				let have = self.held.len();
+				self.held.resize(have + buf.len().max(TAG_LEN), 0);
+				let got = self.r.read(&mut self.held[have ..]);
+				self.held.truncate(have + *got.as_ref().unwrap_or(&0));
+				self.done = got? == 0;
+			}
+			if self.done {
+				check_trailer(mac, &self.held)?;
+				return Ok(0);
+			}
+		}
+	}
+}
+
+// at EOF what's held must be exactly the trailer. asked again on every read
+// after EOF, so a failure isn't forgotten.
+#[cfg(feature = "cipher")]
+fn check_trailer(mac: &SpritzMac, held: &[u8]) -> Result<()> {
+	if held.len() < TAG_LEN {
+		return Err(Error::Truncated);
+	}
+	mac.clone().verify(&Tag::try_from(held)?)
+}
+
+// the chunked format written by EncryptWriter:
+//
+//   magic (4) || chunk size (4, big endian) || chunk* || final chunk
+//
+// each chunk is chunk-size bytes of ciphertext plus a tag; the final one is
+// shorter (possibly just a tag) and sealed with a "last" flag, so a stream
+// cut at a chunk boundary still fails to verify. chunk i is sealed under
+// chunk_nonce(nonce, i), and the header is associated data for every chunk.
+const MAGIC: &[u8; 4] = b"SPZ\x02";
+const HEADER_LEN: usize = 8;
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+// bigger sizes in a header are refused rather than allocated.
+const MAX_CHUNK_SIZE: usize = 16 << 20;
+
+// a hash of the stream's nonce and the chunk number, so chunks of two
+// streams only share a nonce if the streams do, whatever the nonces look
+// like. xoring i into the nonce would give chunk 1 under from_counter(5)
+// the nonce of chunk 0 under from_counter(4).
+pub(crate) fn chunk_nonce(nonce: &Nonce, i: u64) -> Nonce {
+	let mut sp = Spritz::initialize_state();
+	sp.absorb(b"spritz chunk nonce");
+	sp.absorb_stop();
+	sp.absorb(nonce.as_bytes());
+	sp.absorb_stop();
+	sp.absorb(&i.to_be_bytes());
+	sp.absorb_stop();
+	sp.absorb(&[NONCE_LEN as u8]);
+	let mut n = [0; NONCE_LEN];
+	sp.squeeze_into(&mut n);
+	Nonce::new(n)
+}
+
+fn chunk_ad(header: &[u8; HEADER_LEN], last: bool) -> [u8; HEADER_LEN + 1] {
+	let mut ad = [0; HEADER_LEN + 1];
+	ad[.. HEADER_LEN].copy_from_slice(header);
+	ad[HEADER_LEN] = last as u8;
+	ad
+}
+
+fn header(chunk_size: usize) -> [u8; HEADER_LEN] {
+	let mut h = [0; HEADER_LEN];
+	h[.. 4].copy_from_slice(MAGIC);
+	h[4 ..].copy_from_slice(&(chunk_size as u32).to_be_bytes());
+	h
+}
+
+// encrypts everything written to it into the chunked format. finish() must
+// be called to write the final chunk; a stream that is just dropped will
+// fail to decrypt as truncated. a full chunk is sealed once, into a buffer
+// of its own, and written out at the start of the next write, flush or
+// finish, so a failed write of the inner writer can be retried without
+// sealing anything twice.
+pub struct EncryptWriter<W: Write> {
+	w: W,
+	aead: SpritzAead,
+	nonce: Nonce,
+	header: [u8; HEADER_LEN],
+	buf: Vec<u8>,
+	// sealed bytes, starting with the header, not yet taken by w.
+	out: Vec<u8>,
+	counter: u64,
+	chunk_size: usize,
+}
+
+impl<W: Write> EncryptWriter<W> {
+	pub fn new(w: W, key: &Key, nonce: &Nonce) -> EncryptWriter<W> {
+		EncryptWriter::with_chunk_size(w, key, nonce, DEFAULT_CHUNK_SIZE).unwrap()
+	}
+
+	// chunk_size is recorded in the header, so the reader needs no setting.
+	// smaller chunks mean less buffering, larger ones less tag overhead.
+	pub fn with_chunk_size(w: W, key: &Key, nonce: &Nonce, chunk_size: usize) -> Result<EncryptWriter<W>> {
+		if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+			return Err(Error::InvalidLength);
+		}
+		let header = header(chunk_size);
+		let mut out = Vec::with_capacity(chunk_size + TAG_LEN);
+		out.extend_from_slice(&header);
+		Ok(EncryptWriter {
+			w,
+			aead: SpritzAead::new(key),
+			nonce: *nonce,
+			header,
+			buf: Vec::with_capacity(chunk_size),
+			out,
+			counter: 0,
+			chunk_size,
+		})
+	}
+
+	// the counter moves on as the chunk is sealed, whether or not it's
+	// written yet.
+	fn seal_chunk(&mut self, last: bool) {
+		let nonce = chunk_nonce(&self.nonce, self.counter);
+		self.counter += 1;
+		let start = self.out.len();
+		self.out.extend_from_slice(&self.buf);
+		self.buf.clear();
+		let tag = self.aead.seal_in_place_detached(&nonce, &chunk_ad(&self.header, last), &mut self.out[start ..]);
+		self.out.extend_from_slice(tag.as_bytes());
+	}
+
+	fn write_out(&mut self) -> io::Result<()> {
+		while !self.out.is_empty() {
+			match self.w.write(&self.out) {
+				Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+				Ok(n) => {
+					self.out.drain(.. n);
+				}
+				Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+				Err(e) => return Err(e),
+			}
+		}
+		Ok(())
+	}
+
+	// write the final chunk and hand back the inner writer.
+	pub fn finish(mut self) -> io::Result<W> {
+		self.write_out()?;
+		self.seal_chunk(true);
+		self.write_out()?;
+		self.w.flush()?;
+		Ok(self.w)
+	}
+}
+
+impl<W: Write> Write for EncryptWriter<W> {
+	fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+		let mut n = 0;
+		while n < data.len() {
+			// an error once some of data is taken waits for the next call.
+			if let Err(e) = self.write_out() {
+				return if n == 0 { Err(e) } else { Ok(n) };
+			}
+			let m = (self.chunk_size - self.buf.len()).min(data.len() - n);
+			self.buf.extend_from_slice(&data[n .. n + m]);
+			n += m;
+			if self.buf.len() == self.chunk_size {
+				self.seal_chunk(false);
+			}
+		}
+		Ok(n)
+	}
+
+	// every slice goes straight into the chunk buffer, so a header and body
+	// written together can share a chunk without being joined first.
+	fn write_vectored(&mut self, bufs: &[IoSlice]) -> io::Result<usize> {
+		let mut total = 0;
+		for b in bufs {
+			let n = match self.write(b) {
+				Err(e) if total == 0 => return Err(e),
+				Err(_) => break,
+				Ok(n) => n,
+			};
+			total += n;
+			if n < b.len() {
+				break;
+			}
+		}
+		Ok(total)
+	}
+
+	// only flushes whole chunks; the partial one waits for more data or finish().
+	fn flush(&mut self) -> io::Result<()> {
+		self.write_out()?;
+		self.w.flush()
+	}
+}
+
+// decrypts the chunked format. each chunk is verified before any of it is
+// returned, so a reader never sees unauthenticated plaintext; a bad tag or
+// a truncated stream is an InvalidData error carrying an Error, and every
+// read after it fails the same way.
+pub struct DecryptReader<R: Read> {
+	r: R,
+	aead: SpritzAead,
+	nonce: Nonce,
+	header: [u8; HEADER_LEN],
+	chunk_size: usize,
+	buf: Vec<u8>,
+	pos: usize,
+	counter: u64,
+	started: bool,
+	done: bool,
+	failed: Option<Error>,
+}
+
+// fill buf from r, stopping early only at EOF.
+fn read_full<R: Read>(r: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+	let mut n = 0;
+	while n < buf.len() {
+		match r.read(&mut buf[n ..]) {
+			Ok(0) => break,
+			Ok(m) => n += m,
+			Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+			Err(e) => return Err(e),
+		}
+	}
+	Ok(n)
+}
+
+impl<R: Read> DecryptReader<R> {
+	pub fn new(r: R, key: &Key, nonce: &Nonce) -> DecryptReader<R> {
+		DecryptReader {
+			r,
+			aead: SpritzAead::new(key),
+			nonce: *nonce,
+			header: [0; HEADER_LEN],
+			chunk_size: 0,
+			buf: vec![],
+			pos: 0,
+			counter: 0,
+			started: false,
+			done: false,
+			failed: None,
+		}
+	}
+
+	fn read_header(&mut self) -> io::Result<()> {
+		if read_full(&mut self.r, &mut self.header)? != HEADER_LEN {
+			return Err(Error::Truncated.into());
+		}
+		let mut size = [0; 4];
+		size.copy_from_slice(&self.header[4 ..]);
+		let size = u32::from_be_bytes(size) as usize;
+		if &self.header[.. 4] != MAGIC || size == 0 || size > MAX_CHUNK_SIZE {
+			return Err(Error::InvalidEncoding.into());
+		}
+		self.chunk_size = size;
+		self.started = true;
+		Ok(())
+	}
+
+	fn next_chunk(&mut self) -> io::Result<()> {
+		self.buf.resize(self.chunk_size + TAG_LEN, 0);
+		let n = read_full(&mut self.r, &mut self.buf)?;
+		if n < TAG_LEN {
+			return Err(Error::Truncated.into());
+		}
+		let last = n < self.buf.len();
+		let m = n - TAG_LEN;
+		let tag = Tag::try_from(&self.buf[m .. n]).map_err(io::Error::from)?;
+		let nonce = chunk_nonce(&self.nonce, self.counter);
+		let ad = chunk_ad(&self.header, last);
+		self.aead.open_in_place_detached(&nonce, &ad, &mut self.buf[.. m], &tag)?;
+		self.buf.truncate(m);
+		self.pos = 0;
+		self.counter += 1;
+		self.done = last;
+		Ok(())
+	}
+
+	fn advance(&mut self) -> io::Result<()> {
+		if !self.started {
+			self.read_header()?;
+		}
+		while self.pos == self.buf.len() && !self.done {
+			self.next_chunk()?;
+		}
+		Ok(())
+	}
+}
+
+impl<R: Read> Read for DecryptReader<R> {
+	fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+		let n = {
+			let buf = self.fill_buf()?;
+			let n = out.len().min(buf.len());
+			out[.. n].copy_from_slice(&buf[.. n]);
+			n
+		};
+		self.consume(n);
+		Ok(n)
+	}
+}
+
+// hands out the current verified chunk directly.
+impl<R: Read> BufRead for DecryptReader<R> {
+	fn fill_buf(&mut self) -> io::Result<&[u8]> {
+		if let Some(e) = self.failed {
+			return Err(e.into());
+		}
+		if let Err(e) = self.advance() {
+			// what's in buf may be a chunk that failed to verify.
+			self.buf.clear();
+			self.pos = 0;
+			self.failed = e.get_ref().and_then(|e| e.downcast_ref::<Error>()).copied();
+			return Err(e);
+		}
+		Ok(&self.buf[self.pos ..])
+	}
+
+	fn consume(&mut self, n: usize) {
+		self.pos = (self.pos + n).min(self.buf.len());
+	}
+}
+
+// io::copy through the chunked format, finishing the stream at the end.
+// returns the plaintext bytes copied.
+pub fn copy_encrypt<R: Read, W: Write>(r: &mut R, w: W, key: &Key, nonce: &Nonce) -> io::Result<u64> {
+	let mut enc = EncryptWriter::new(w, key, nonce);
+	let n = io::copy(r, &mut enc)?;
+	enc.finish()?;
+	Ok(n)
+}
+
+// on error, some verified plaintext may already have been written to w.
+pub fn copy_decrypt<R: Read, W: Write>(r: R, w: &mut W, key: &Key, nonce: &Nonce) -> io::Result<u64> {
+	let n = io::copy(&mut DecryptReader::new(r, key, nonce), w)?;
+	w.flush()?;
+	Ok(n)
+}
+
+// the same output as copy_encrypt, with the chunks of each batch sealed on
+// separate threads. chunk nonces and associated data only depend on the
+// chunk's position, so the chunks can be sealed in any order.
+pub fn encrypt_parallel<R: Read, W: Write>(mut r: R, mut w: W, key: &Key, nonce: &Nonce, threads: usize) -> io::Result<u64> {
+	let aead = SpritzAead::new(key);
+	let h = header(DEFAULT_CHUNK_SIZE);
+	w.write_all(&h)?;
+	let mut chunks = vec![vec![]; threads.max(1)];
+	let (mut counter, mut total) = (0, 0);
+	loop {
+		// a short read is the final chunk, which may be empty.
+		let (mut n, mut last) = (0, false);
+		for c in chunks.iter_mut() {
+			c.resize(DEFAULT_CHUNK_SIZE, 0);
+			let got = read_full(&mut r, c)?;
+			c.truncate(got);
+			n += 1;
+			if got < DEFAULT_CHUNK_SIZE {
+				last = true;
+				break;
+			}
+		}
+		let batch = &mut chunks[.. n];
+		thread::scope(|s| {
+			for (i, c) in batch.iter_mut().enumerate() {
+				let (aead, h) = (&aead, &h);
+				let nonce = chunk_nonce(nonce, counter + i as u64);
+				let ad = chunk_ad(h, last && i == n - 1);
+				s.spawn(move || {
+					let tag = aead.seal_in_place_detached(&nonce, &ad, c);
+					c.extend_from_slice(tag.as_bytes());
+				});
+			}
+		});
+		for c in batch.iter() {
+			w.write_all(c)?;
+			total += (c.len() - TAG_LEN) as u64;
+		}
+		counter += n as u64;
+		if last {
+			w.flush()?;
+			return Ok(total);
+		}
+	}
+}
+
+// the same chunking for pipelines that already deal in discrete buffers
+// (upload parts, message streams): each input item becomes one frame of
+//
+//   sequence number (8, big endian) || last flag (1) || ciphertext || tag
+//
+// sealed like the stream format, with frame i under chunk_nonce(nonce, i).
+// a futures Stream adapter would wrap these.
+const CHUNKS_HEADER: [u8; HEADER_LEN] = [b'S', b'P', b'Z', b'C', 0, 0, 0, 0];
+const FRAME_PREFIX: usize = 9;
+
+pub struct SealChunks<I: Iterator<Item = Vec<u8>>> {
+	it: Peekable<I>,
+	aead: SpritzAead,
+	nonce: Nonce,
+	counter: u64,
+	done: bool,
+}
+
+// an empty input still yields one (empty, last) frame.
+pub fn seal_chunks<I: IntoIterator<Item = Vec<u8>>>(it: I, key: &Key, nonce: &Nonce) -> SealChunks<I::IntoIter> {
+	SealChunks { it: it.into_iter().peekable(), aead: SpritzAead::new(key), nonce: *nonce, counter: 0, done: false }
+}
+
+impl<I: Iterator<Item = Vec<u8>>> Iterator for SealChunks<I> {
+	type Item = Vec<u8>;
+
+	fn next(&mut self) -> Option<Vec<u8>> {
+		if self.done {
+			return None;
+		}
+		let chunk = self.it.next().unwrap_or_default();
+		let last = self.it.peek().is_none();
+		let mut frame = Vec::with_capacity(FRAME_PREFIX + chunk.len() + TAG_LEN);
+		frame.extend_from_slice(&self.counter.to_be_bytes());
+		frame.push(last as u8);
+		frame.extend_from_slice(&chunk);
+		let nonce = chunk_nonce(&self.nonce, self.counter);
+		let tag = self.aead.seal_in_place_detached(&nonce, &chunk_ad(&CHUNKS_HEADER, last), &mut frame[FRAME_PREFIX ..]);
+		frame.extend_from_slice(tag.as_bytes());
+		self.counter += 1;
+		self.done = last;
+		Some(frame)
+	}
+}
+
+pub struct OpenChunks<I: Iterator<Item = Vec<u8>>> {
+	it: I,
+	aead: SpritzAead,
+	nonce: Nonce,
+	counter: u64,
+	done: bool,
+}
+
+// yields an error and stops at the first frame that is out of order, fails
+// to verify, or is missing (the input ended before a last frame).
+pub fn open_chunks<I: IntoIterator<Item = Vec<u8>>>(it: I, key: &Key, nonce: &Nonce) -> OpenChunks<I::IntoIter> {
+	OpenChunks { it: it.into_iter(), aead: SpritzAead::new(key), nonce: *nonce, counter: 0, done: false }
+}
+
+impl<I: Iterator<Item = Vec<u8>>> OpenChunks<I> {
+	fn open(&mut self, mut frame: Vec<u8>) -> Result<(Vec<u8>, bool)> {
+		if frame.len() < FRAME_PREFIX + TAG_LEN || frame[8] > 1 {
+			return Err(Error::InvalidLength);
+		}
+		if frame[.. 8] != self.counter.to_be_bytes() {
+			return Err(Error::UnexpectedSequence);
+		}
+		let last = frame[8] == 1;
+		let m = frame.len() - TAG_LEN;
+		let tag = Tag::try_from(&frame[m ..])?;
+		let nonce = chunk_nonce(&self.nonce, self.counter);
+		self.aead.open_in_place_detached(&nonce, &chunk_ad(&CHUNKS_HEADER, last), &mut frame[FRAME_PREFIX .. m], &tag)?;
+		frame.truncate(m);
+		frame.drain(.. FRAME_PREFIX);
+		Ok((frame, last))
+	}
+}
+
+impl<I: Iterator<Item = Vec<u8>>> Iterator for OpenChunks<I> {
+	type Item = Result<Vec<u8>>;
+
+	fn next(&mut self) -> Option<Result<Vec<u8>>> {
+		if self.done {
+			return None;
+		}
+		let r = match self.it.next() {
+			None => Err(Error::Truncated),
+			Some(frame) => self.open(frame),
+		};
+		self.counter += 1;
+		Some(match r {
+			Ok((chunk, last)) => {
+				self.done = last;
+				Ok(chunk)
+			}
+			Err(e) => {
+				self.done = true;
+				Err(e)
+			}
+		})
+	}
+}
+
+#[cfg(feature = "cipher")]
+#[test]
+fn keystream_reader() {
+	let mut r = KeystreamReader::new(Spritz::with_raw_key(b"ABC").deterministic());
+	let mut buf = [0; 8];
+	r.read_exact(&mut buf).unwrap();
+	assert_eq!(buf, [0x77, 0x9a, 0x8e, 0x01, 0xf9, 0xe9, 0xcb, 0xc0]);
+
+	let mut v = vec![];
+	KeystreamReader::new(Spritz::with_raw_key(b"ABC").deterministic()).take(4).read_to_end(&mut v).unwrap();
+	assert_eq!(v, &buf[.. 4]);
+}
+
+#[test]
+fn encrypt_writer() {
+	let key = Key::new([1; 32]);
+	let nonce = Nonce::from_counter(5);
+	let data: Vec<u8> = (0 .. 2 * DEFAULT_CHUNK_SIZE + 10).map(|i| i as u8).collect();
+	let mut w = EncryptWriter::new(vec![], &key, &nonce);
+	w.write_all(&data[.. 10]).unwrap();
+	w.write_all(&data[10 ..]).unwrap();
+	let out = w.finish().unwrap();
+	assert_eq!(out.len(), HEADER_LEN + data.len() + 3 * TAG_LEN);
+
+	let h = header(DEFAULT_CHUNK_SIZE);
+	let a = SpritzAead::new(&key);
+	let c = DEFAULT_CHUNK_SIZE + TAG_LEN;
+	let first = &out[HEADER_LEN .. HEADER_LEN + c];
+	assert_eq!(a.open(&chunk_nonce(&nonce, 0), &chunk_ad(&h, false), first).unwrap(), &data[.. DEFAULT_CHUNK_SIZE]);
+	let last = &out[HEADER_LEN + 2 * c ..];
+	assert_eq!(a.open(&chunk_nonce(&nonce, 2), &chunk_ad(&h, true), last).unwrap(), &data[2 * DEFAULT_CHUNK_SIZE ..]);
+
+	// an exact multiple still ends with a (empty) final chunk.
+	let mut w = EncryptWriter::new(vec![], &key, &nonce);
+	w.write_all(&data[.. DEFAULT_CHUNK_SIZE]).unwrap();
+	assert_eq!(w.finish().unwrap().len(), HEADER_LEN + DEFAULT_CHUNK_SIZE + 2 * TAG_LEN);
+
+	// chunk 1 under counter nonce 5 and chunk 0 under counter nonce 4 are
+	// sealed under different nonces.
+	let two = |n| {
+		let mut w = EncryptWriter::with_chunk_size(vec![], &key, &Nonce::from_counter(n), 16).unwrap();
+		w.write_all(&[0; 32]).unwrap();
+		w.finish().unwrap()
+	};
+	let (a, b) = (two(5), two(4));
+	let c = 16 + TAG_LEN;
+	assert!(a[HEADER_LEN + c .. HEADER_LEN + 2 * c] != b[HEADER_LEN .. HEADER_LEN + c]);
+}
+
+// a writer whose writes with the given numbers fail.
+#[cfg(test)]
+struct Flaky {
+	out: Vec<u8>,
+	calls: usize,
+	fail: &'static [usize],
+}
+
+#[cfg(test)]
+impl Write for Flaky {
+	fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+		self.calls += 1;
+		if self.fail.contains(&(self.calls - 1)) {
+			return Err(io::ErrorKind::WouldBlock.into());
+		}
+		self.out.write(data)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+#[test]
+fn encrypt_writer_retry() {
+	let key = Key::new([1; 32]);
+	let nonce = Nonce::from_counter(5);
+	let mut w = EncryptWriter::with_chunk_size(Flaky { out: vec![], calls: 0, fail: &[0, 2, 3] }, &key, &nonce, 16).unwrap();
+	assert!(w.write(b"attack at dawn!!").is_err());
+	w.write_all(b"attack at dawn!!").unwrap();
+	assert!(w.flush().is_err());
+	assert!(w.write(b"retreat at dusk!").is_err());
+	w.write_all(b"retreat at dusk!").unwrap();
+	let ct = w.finish().unwrap().out;
+	assert!(!ct.windows(16).any(|c| c == b"attack at dawn!!" || c == b"retreat at dusk!"));
+	let mut pt = vec![];
+	DecryptReader::new(&ct[..], &key, &nonce).read_to_end(&mut pt).unwrap();
+	assert_eq!(pt, b"attack at dawn!!retreat at dusk!");
+}
+
+#[test]
+fn decrypt_reader() {
+	let key = Key::new([1; 32]);
+	let nonce = Nonce::from_counter(5);
+	let data: Vec<u8> = (0 .. DEFAULT_CHUNK_SIZE + 100).map(|i| i as u8).collect();
+	let mut w = EncryptWriter::new(vec![], &key, &nonce);
+	w.write_all(&data).unwrap();
+	let out = w.finish().unwrap();
+
+	let mut pt = vec![];
+	DecryptReader::new(&out[..], &key, &nonce).read_to_end(&mut pt).unwrap();
+	assert_eq!(pt, data);
+
+	let err = |ct: &[u8], nonce: &Nonce| {
+		let mut pt = vec![];
+		let e = DecryptReader::new(ct, &key, nonce).read_to_end(&mut pt).err().unwrap();
+		// nothing unauthenticated was handed out before the error.
+		assert!(pt.len() % DEFAULT_CHUNK_SIZE == 0);
+		*e.get_ref().unwrap().downcast_ref::<Error>().unwrap()
+	};
+	assert_eq!(err(&out, &Nonce::from_counter(6)), Error::InvalidTag);
+	let mut bad = out.clone();
+	bad[out.len() - 50] ^= 1;
+	assert_eq!(err(&bad, &nonce), Error::InvalidTag);
+	assert_eq!(err(&out[.. HEADER_LEN + DEFAULT_CHUNK_SIZE + TAG_LEN], &nonce), Error::Truncated);
+	assert_eq!(err(&out[.. out.len() - 40], &nonce), Error::InvalidTag);
+	assert_eq!(err(&out[.. 3], &nonce), Error::Truncated);
+
+	// reading again after a failure fails again rather than handing out
+	// what was left in the buffer.
+	let mut r = DecryptReader::new(&bad[..], &key, &nonce);
+	let mut buf = [0; 48];
+	assert_eq!(r.read(&mut buf).unwrap(), 48);
+	r.read_exact(&mut vec![0; DEFAULT_CHUNK_SIZE - 48]).unwrap();
+	assert_eq!(r.read(&mut buf).unwrap_err().kind(), io::ErrorKind::InvalidData);
+	for _ in 0 .. 2 {
+		let e = r.read(&mut buf).unwrap_err();
+		assert_eq!(e.get_ref().unwrap().downcast_ref::<Error>(), Some(&Error::InvalidTag));
+		assert!(r.fill_buf().is_err());
+	}
+}
+
+#[test]
+fn buffered() {
+	let key = Key::new([1; 32]);
+	let nonce = Nonce::from_counter(0);
+	assert!(EncryptWriter::with_chunk_size(vec![], &key, &nonce, 0).is_err());
+	let mut w = EncryptWriter::with_chunk_size(vec![], &key, &nonce, 7).unwrap();
+	for i in 0 .. 5 {
+		writeln!(w, "line {}", i).unwrap();
+	}
+	let ct = w.finish().unwrap();
+	assert_eq!(ct.len(), HEADER_LEN + 35 + 6 * TAG_LEN);
+
+	let r = DecryptReader::new(&ct[..], &key, &nonce);
+	let lines: Vec<String> = r.lines().map(|l| l.unwrap()).collect();
+	assert_eq!(lines, ["line 0", "line 1", "line 2", "line 3", "line 4"]);
+}
+
+#[test]
+fn chunks() {
+	let key = Key::new([2; 32]);
+	let nonce = Nonce::from_counter(9);
+	let parts = vec![b"part one".to_vec(), b"two".to_vec(), vec![]];
+	let sealed: Vec<Vec<u8>> = seal_chunks(parts.clone(), &key, &nonce).collect();
+	assert_eq!(sealed.len(), 3);
+	assert_eq!(sealed[2][8], 1);
+	let opened: Result<Vec<Vec<u8>>> = open_chunks(sealed.clone(), &key, &nonce).collect();
+	assert_eq!(opened.unwrap(), parts);
+
+	let empty: Vec<Vec<u8>> = seal_chunks(vec![], &key, &nonce).collect();
+	assert_eq!(empty.len(), 1);
+
+	let r: Vec<Result<Vec<u8>>> = open_chunks(sealed[.. 2].to_vec(), &key, &nonce).collect();
+	assert_eq!(r.last().unwrap().clone().err(), Some(Error::Truncated));
+	let swapped = vec![sealed[1].clone(), sealed[0].clone()];
+	let r: Vec<Result<Vec<u8>>> = open_chunks(swapped, &key, &nonce).collect();
+	assert_eq!(r, [Err(Error::UnexpectedSequence)]);
+
+	// frame 1 under counter nonce 9 isn't frame 0 under counter nonce 8.
+	let other: Vec<Vec<u8>> = seal_chunks(vec![b"two".to_vec(), vec![]], &key, &Nonce::from_counter(8)).collect();
+	assert!(other[0][FRAME_PREFIX ..] != sealed[1][FRAME_PREFIX ..]);
+}
+
+#[test]
+fn write_vectored() {
+	let key = Key::new([3; 32]);
+	let nonce = Nonce::from_counter(4);
+	let mut a = EncryptWriter::with_chunk_size(vec![], &key, &nonce, 8).unwrap();
+	let n = a.write_vectored(&[IoSlice::new(b"head"), IoSlice::new(b""), IoSlice::new(b"er and body")]).unwrap();
+	assert_eq!(n, 15);
+	let mut b = EncryptWriter::with_chunk_size(vec![], &key, &nonce, 8).unwrap();
+	b.write_all(b"header and body").unwrap();
+	assert_eq!(a.finish().unwrap(), b.finish().unwrap());
+}
+
+#[test]
+fn copy() {
+	let key = Key::new([5; 32]);
+	let nonce = Nonce::from_counter(6);
+	let data = vec![1; 200000];
+	let mut ct = vec![];
+	assert_eq!(copy_encrypt(&mut &data[..], &mut ct, &key, &nonce).unwrap(), 200000);
+	let mut pt = vec![];
+	assert_eq!(copy_decrypt(&ct[..], &mut pt, &key, &nonce).unwrap(), 200000);
+	assert_eq!(pt, data);
+	assert!(copy_decrypt(&ct[.. ct.len() - 1], &mut vec![], &key, &nonce).is_err());
+}
+
+#[test]
+fn parallel() {
+	let key = Key::new([6; 32]);
+	let nonce = Nonce::from_counter(7);
+	for len in [0, 100, DEFAULT_CHUNK_SIZE, 5 * DEFAULT_CHUNK_SIZE + 3] {
+		let data: Vec<u8> = (0 .. len).map(|i| i as u8).collect();
+		let mut want = vec![];
+		copy_encrypt(&mut &data[..], &mut want, &key, &nonce).unwrap();
+		let mut got = vec![];
+		assert_eq!(encrypt_parallel(&data[..], &mut got, &key, &nonce, 3).unwrap(), len as u64);
+		assert!(got == want);
+	}
+}
+
+#[cfg(feature = "cipher")]
+#[test]
+fn xor_trailer() {
+	let data: Vec<u8> = (0 .. 10000).map(|i| i as u8).collect();
+	let mut w = XorWriter::new(vec![], Spritz::with_raw_key(b"key").deterministic());
+	w.write_all(&data).unwrap();
+	let plain = w.finish().unwrap();
+	let mut want = data.clone();
+	Spritz::with_raw_key(b"key").deterministic().apply_keystream(&mut want);
+	assert_eq!(plain, want);
+	let mut back = vec![];
+	XorReader::new(&plain[..], Spritz::with_raw_key(b"key").deterministic()).read_to_end(&mut back).unwrap();
+	assert_eq!(back, data);
+
+	let mut w = XorWriter::with_mac(vec![], Spritz::with_raw_key(b"key").deterministic(), b"mac key");
+	w.write_all(&data[.. 5000]).unwrap();
+	w.write_all(&data[5000 ..]).unwrap();
+	let checked = w.finish().unwrap();
+	assert_eq!(checked[.. data.len()], plain[..]);
+	assert_eq!(checked.len(), data.len() + TAG_LEN);
+	let read = |ct: &[u8]| {
+		let mut out = vec![];
+		XorReader::with_mac(ct, Spritz::with_raw_key(b"key").deterministic(), b"mac key").read_to_end(&mut out).map(|_| out)
+	};
+	assert_eq!(read(&checked).unwrap(), data);
+	let mut bad = checked.clone();
+	bad[17] ^= 1;
+	let err = |r: io::Result<Vec<u8>>| *r.unwrap_err().get_ref().unwrap().downcast_ref::<Error>().unwrap();
+	assert_eq!(err(read(&bad)), Error::InvalidTag);
+	assert_eq!(err(read(&checked[.. checked.len() - 1])), Error::InvalidTag);
+	assert_eq!(err(read(&checked[.. 5])), Error::Truncated);
+	let empty = XorWriter::with_mac(vec![], Spritz::with_raw_key(b"key").deterministic(), b"mac key").finish().unwrap();
+	assert_eq!(read(&empty).unwrap(), b"");
+
+	// failed writes are retried without encrypting anything twice.
+	let flaky = Flaky { out: vec![], calls: 0, fail: &[0, 2, 3, 4] };
+	let mut w = XorWriter::with_mac(flaky, Spritz::with_raw_key(b"key").deterministic(), b"mac key");
+	assert_eq!(w.write(&data[.. 5000]).unwrap(), XOR_BLOCK);
+	assert_eq!(w.write(&data[XOR_BLOCK .. 5000]).unwrap(), 5000 - XOR_BLOCK);
+	assert!(w.flush().is_err());
+	assert!(w.write(&data[5000 ..]).is_err());
+	w.write_all(&data[5000 ..]).unwrap();
+	assert_eq!(w.finish().unwrap().out, checked);
+}
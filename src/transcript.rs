@@ -0,0 +1,67 @@
+// transcript hashing for handshakes.
+//
+// labels and messages go into separate protocol operations, so
+// append("a", b"bc") and append("ab", b"c") can't collide.
+
+use protocol::Protocol;
+
+#[derive(Clone, Debug)]
+pub struct Transcript {
+	p: Protocol,
+}
+
+impl Transcript {
+	pub fn new(label: &str) -> Transcript {
+		let mut p = Protocol::new(b"spritz transcript");
+		p.ad(label.as_bytes());
+		Transcript { p }
+	}
+
+	pub fn append(&mut self, label: &str, msg: &[u8]) {
+		self.p.ad(label.as_bytes());
+		self.p.ad(msg);
+	}
+
+	// challenge bytes bound to everything appended so far. the challenge
+	// itself becomes part of the transcript.
+	pub fn challenge(&mut self, label: &str, out: &mut [u8]) {
+		self.p.ad(label.as_bytes());
+		self.p.prf(out);
+	}
+
+	// a keyed tag over the transcript so far, e.g. a PSK binder. the
+	// transcript is left untouched.
+	pub fn binder(&self, label: &str, key: &[u8], out: &mut [u8]) {
+		let mut p = self.p.clone();
+		p.ad(label.as_bytes());
+		p.key(key);
+		p.send_mac(out);
+	}
+}
+
+#[test]
+fn transcript() {
+	let mut a = Transcript::new("handshake");
+	let mut b = Transcript::new("handshake");
+	a.append("client_hello", b"ab");
+	b.append("client_hello", b"ab");
+
+	let (mut x, mut y) = ([0; 16], [0; 16]);
+	a.binder("psk", b"key", &mut x);
+	b.binder("psk", b"key", &mut y);
+	assert_eq!(x, y);
+	b.binder("psk", b"other", &mut y);
+	assert!(x != y);
+
+	a.challenge("c", &mut x);
+	b.challenge("c", &mut y);
+	assert_eq!(x, y);
+
+	let mut c = Transcript::new("handshake");
+	let mut d = Transcript::new("handshake");
+	c.append("a", b"bc");
+	d.append("ab", b"c");
+	c.challenge("c", &mut x);
+	d.challenge("c", &mut y);
+	assert!(x != y);
+}
@@ -0,0 +1,201 @@
+// small single-file encrypted key-value store.
+//
+//   magic (8) || generation (8) || index nonce (16) || index length (4)
+//   || sealed index || sealed values
+//
+// every value is sealed under its own random nonce with its name as
+// associated data. the index lists, for each entry, the name, nonce and
+// sealed value, and is sealed itself with the magic and generation as
+// associated data, so names stay secret and entries can't be removed,
+// swapped or rolled back one at a time. save() writes a new file next to
+// the old one and renames it over.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use aead::{SpritzAead, TAG_LEN};
+use ct_eq;
+use error::{Error, Result};
+use key::{Key, Nonce, NONCE_LEN};
+use rng::SpritzRng;
+
+const MAGIC: &[u8; 8] = b"SPZVLT\x00\x01";
+const HEADER_LEN: usize = 8 + 8 + NONCE_LEN + 4;
+
+struct Entry {
+	nonce: Nonce,
+	sealed: Vec<u8>,
+}
+
+pub struct SpritzVault {
+	path: PathBuf,
+	aead: SpritzAead,
+	rng: SpritzRng,
+	generation: u64,
+	entries: BTreeMap<String, Entry>,
+}
+
+fn entry_ad(name: &str) -> Vec<u8> {
+	let mut ad = MAGIC.to_vec();
+	ad.extend_from_slice(name.as_bytes());
+	ad
+}
+
+fn take<'a>(buf: &mut &'a [u8], n: usize) -> Result<&'a [u8]> {
+	if buf.len() < n {
+		return Err(Error::Truncated);
+	}
+	let (a, b) = buf.split_at(n);
+	*buf = b;
+	Ok(a)
+}
+
+fn take_u32(buf: &mut &[u8]) -> Result<usize> {
+	let mut b = [0; 4];
+	b.copy_from_slice(take(buf, 4)?);
+	Ok(u32::from_be_bytes(b) as usize)
+}
+
+fn take_nonce(buf: &mut &[u8]) -> Result<Nonce> {
+	let mut n = [0; NONCE_LEN];
+	n.copy_from_slice(take(buf, NONCE_LEN)?);
+	Ok(Nonce::new(n))
+}
+
+impl SpritzVault {
+	// an empty vault; nothing is written until save().
+	pub fn create<P: AsRef<Path>>(path: P, key: &Key) -> io::Result<SpritzVault> {
+		Ok(SpritzVault {
+			path: path.as_ref().to_path_buf(),
+			aead: SpritzAead::new(key),
+			rng: SpritzRng::from_os()?,
+			generation: 0,
+			entries: BTreeMap::new(),
+		})
+	}
+
+	pub fn open<P: AsRef<Path>>(path: P, key: &Key) -> io::Result<SpritzVault> {
+		let mut v = SpritzVault::create(&path, key)?;
+		let mut buf = vec![];
+		File::open(&path)?.read_to_end(&mut buf)?;
+		v.load(&buf)?;
+		Ok(v)
+	}
+
+	fn load(&mut self, mut buf: &[u8]) -> Result<()> {
+		let buf = &mut buf;
+		let header = take(buf, HEADER_LEN)?;
+		if &header[.. 8] != MAGIC {
+			return Err(Error::InvalidLength);
+		}
+		let mut g = [0; 8];
+		g.copy_from_slice(&header[8 .. 16]);
+		let mut h = &header[16 ..];
+		let nonce = take_nonce(&mut h)?;
+		let len = take_u32(&mut h)?;
+		let index = self.aead.open(&nonce, &header[.. 16], take(buf, len)?)?;
+
+		let mut idx = &index[..];
+		let count = take_u32(&mut idx)?;
+		for _ in 0 .. count {
+			let n = take_u32(&mut idx)?;
+			let name = String::from_utf8(take(&mut idx, n)?.to_vec()).map_err(|_| Error::InvalidLength)?;
+			let nonce = take_nonce(&mut idx)?;
+			let n = take_u32(&mut idx)?;
+			let tag = take(&mut idx, TAG_LEN)?;
+			let sealed = take(buf, n)?;
+			if n < TAG_LEN || !ct_eq(&sealed[n - TAG_LEN ..], tag) {
+				return Err(Error::InvalidTag);
+			}
+			self.entries.insert(name, Entry { nonce, sealed: sealed.to_vec() });
+		}
+		if !buf.is_empty() || !idx.is_empty() {
+			return Err(Error::InvalidLength);
+		}
+		self.generation = u64::from_be_bytes(g);
+		Ok(())
+	}
+
+	pub fn get(&self, name: &str) -> Result<Option<Vec<u8>>> {
+		match self.entries.get(name) {
+			None => Ok(None),
+			Some(e) => Ok(Some(self.aead.open(&e.nonce, &entry_ad(name), &e.sealed)?)),
+		}
+	}
+
+	pub fn put(&mut self, name: &str, value: &[u8]) {
+		let nonce = Nonce::generate(&mut self.rng);
+		let sealed = self.aead.seal(&nonce, &entry_ad(name), value);
+		self.entries.insert(name.to_string(), Entry { nonce, sealed });
+	}
+
+	pub fn delete(&mut self, name: &str) -> bool {
+		self.entries.remove(name).is_some()
+	}
+
+	pub fn names(&self) -> Vec<&str> {
+		self.entries.keys().map(|k| k.as_str()).collect()
+	}
+
+	pub fn save(&mut self) -> io::Result<()> {
+		let generation = self.generation + 1;
+		let mut index = vec![];
+		index.extend_from_slice(&(self.entries.len() as u32).to_be_bytes());
+		let mut values = vec![];
+		for (name, e) in &self.entries {
+			index.extend_from_slice(&(name.len() as u32).to_be_bytes());
+			index.extend_from_slice(name.as_bytes());
+			index.extend_from_slice(e.nonce.as_bytes());
+			index.extend_from_slice(&(e.sealed.len() as u32).to_be_bytes());
+			index.extend_from_slice(&e.sealed[e.sealed.len() - TAG_LEN ..]);
+			values.extend_from_slice(&e.sealed);
+		}
+
+		let mut out = MAGIC.to_vec();
+		out.extend_from_slice(&generation.to_be_bytes());
+		let nonce = Nonce::generate(&mut self.rng);
+		let sealed = self.aead.seal(&nonce, &out, &index);
+		out.extend_from_slice(nonce.as_bytes());
+		out.extend_from_slice(&(sealed.len() as u32).to_be_bytes());
+		out.extend_from_slice(&sealed);
+		out.extend_from_slice(&values);
+
+		let mut tmp = self.path.clone().into_os_string();
+		tmp.push(".tmp");
+		{
+			let mut f = File::create(&tmp)?;
+			f.write_all(&out)?;
+			f.sync_all()?;
+		}
+		fs::rename(&tmp, &self.path)?;
+		self.generation = generation;
+		Ok(())
+	}
+}
+
+#[test]
+fn vault() {
+	let path = ::std::env::temp_dir().join(format!("spritz-vault-test-{}", ::std::process::id()));
+	let key = Key::new([9; 32]);
+	let mut v = SpritzVault::create(&path, &key).unwrap();
+	v.put("token", b"hunter2");
+	v.put("other", b"x");
+	assert!(v.delete("other"));
+	v.put("password", b"swordfish");
+	v.save().unwrap();
+
+	let v = SpritzVault::open(&path, &key).unwrap();
+	assert_eq!(v.names(), ["password", "token"]);
+	assert_eq!(v.get("token").unwrap().unwrap(), b"hunter2");
+	assert_eq!(v.get("missing").unwrap(), None);
+	assert!(SpritzVault::open(&path, &Key::new([8; 32])).is_err());
+
+	let mut raw = fs::read(&path).unwrap();
+	let n = raw.len();
+	raw[n - 1] ^= 1;
+	fs::write(&path, &raw).unwrap();
+	assert!(SpritzVault::open(&path, &key).is_err());
+	fs::remove_file(&path).unwrap();
+}